@@ -0,0 +1,449 @@
+use crate::DEFAULT_EXTENSIONS;
+use chrono::NaiveDate;
+use collect_memories::{
+    copy_files, find_duplicates, mount_points, parse_date, parse_size, plan_copy,
+    retrieve_files_recursively, CancelToken, ConflictPolicy, CopyOptions, CopyProgress, FileFilter,
+    FileInfo, FileSystemItem, PathTemplate, PlanOptions, ScanOptions, SymlinkPolicy, TransferMode,
+    DEFAULT_EXCLUDE_PATTERNS, DEFAULT_TEMPLATE,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Everything was found and copied.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Command line arguments could not be parsed.
+pub const EXIT_USAGE: i32 = 2;
+/// Source directory could not be scanned, or nothing was found and some
+/// locations could not be read.
+pub const EXIT_SCAN_FAILED: i32 = 3;
+/// No files with memories were found in the source directory.
+pub const EXIT_NOTHING_FOUND: i32 = 4;
+/// Some files could not be copied to the output directory.
+pub const EXIT_COPY_FAILED: i32 = 5;
+/// Several files would be copied to the same destination.
+pub const EXIT_COLLISIONS: i32 = 6;
+/// Found memories were copied, but some locations could not be read.
+pub const EXIT_INCOMPLETE_SCAN: i32 = 7;
+
+static USAGE: &str = "\
+Usage:
+    main                                   Start interactive user interface
+    main --source <DIR> --output <DIR> [OPTIONS]
+
+Options:
+    -s, --source <DIR>        Directory to search for memories
+    -o, --output <DIR>        Directory to copy found memories to
+    -e, --extensions <LIST>   Comma separated list of file extensions
+                              (default: jpeg,jpg,bmp,gif,png,avi,mp4,mpg,mpeg,wmv)
+        --min-size <SIZE>     Skip files smaller than SIZE, e.g. 20K
+        --max-size <SIZE>     Skip files larger than SIZE, e.g. 4G
+        --modified-from <DATE>
+                              Skip files modified before DATE (YYYY-MM-DD)
+        --modified-to <DATE>  Skip files modified after DATE (YYYY-MM-DD)
+    -x, --exclude <PATTERN>   Skip files and directories matching .gitignore style
+                              PATTERN, can be given multiple times
+        --no-default-excludes Do not skip node_modules, AppData, caches, trash
+                              and other directories seldom containing memories
+        --no-ignore-files     Do not read .collectignore files in scanned directories
+        --detect-content      Recognize memories by file content, also files
+                              without or with wrong extension
+    -j, --threads <COUNT>     Scan directories with COUNT threads
+                              (default: one per CPU)
+        --symlinks <POLICY>   Which symbolic links to follow: ignore (default),
+                              files, all - also directories, links back to
+                              a directory containing them are not followed
+    -X, --one-file-system     Do not scan file systems mounted inside the source
+                              directory, e.g. /proc or network drives,
+                              has no effect on Windows
+        --include-mount <DIR> Scan file system mounted at DIR despite
+                              --one-file-system, can be given multiple times
+        --fix-extensions      Give files recognized by content the usual extension
+                              of their format
+        --by-date             Place memories into directories by capture date
+                              (EXIF, video creation time or modification time),
+                              same as --template {year}/{year}-{month}/{original_name}.{ext}
+    -t, --template <TEMPLATE> Generate destination paths instead of mirroring source
+                              directories, placeholders: {year} {month} {day}
+                              {camera_model} {original_name} {ext} {source_dir}
+                              {counter} {hash8}
+    -c, --on-conflict <POLICY>
+                              What to do with already existing destination files:
+                              skip (default), overwrite, newer, rename, identical
+        --verify              Verify copied files by SHA-256 checksum
+        --manifest            Write sha256sum compatible manifest to output directory
+        --mode <MODE>         How memories get to the output directory:
+                              copy (default); move - remove memories from the source
+                              and source directories left empty, files copied to
+                              another file system are verified before removal;
+                              hardlink or reflink - share content with the source
+                              without using extra space, copy when not possible
+    -m, --move                Same as --mode move
+        --no-preserve-times   Do not keep modification and access times of copied
+                              files and directories
+        --preserve-permissions
+                              Keep permissions of copied files and directories
+        --preserve-xattrs     Keep extended attributes of copied files and directories
+        --copy-threads <COUNT>
+                              Copy COUNT files at once, 0 for one per CPU (default: 4),
+                              1 suits spinning hard disks best
+    -d, --skip-duplicates     Copy only the first file (by path) of files with
+                              identical content
+    -r, --failure-report <FILE>
+                              Write list of files that could not be copied
+    -n, --dry-run             Print what would be copied without copying
+    -h, --help                Print this help
+
+Exit codes:
+    0  memories copied
+    2  invalid arguments
+    3  source directory could not be scanned, or nothing found and some
+       locations could not be read
+    4  no memories found
+    5  some files could not be copied
+    6  several files would be copied to the same path, nothing copied
+    7  memories copied, but some locations could not be read";
+
+pub struct Arguments {
+    source: PathBuf,
+    output: PathBuf,
+    filter: FileFilter,
+    scan_options: ScanOptions,
+    plan_options: PlanOptions,
+    options: CopyOptions,
+    skip_duplicates: bool,
+    failure_report: Option<PathBuf>,
+    dry_run: bool,
+}
+
+/// Parses command line arguments (without the program name).
+/// Prints usage and exits the process on `--help` or invalid input.
+pub fn parse_arguments<I>(args: I) -> Arguments
+where
+    I: Iterator<Item = String>,
+{
+    let mut source = None;
+    let mut output = None;
+    let mut filter = FileFilter::new(
+        DEFAULT_EXTENSIONS
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect(),
+    );
+    let mut exclude = Vec::new();
+    let mut default_excludes = true;
+    let mut use_ignore_files = true;
+    let mut detect_content = false;
+    let mut threads = 0;
+    let mut symlinks = SymlinkPolicy::default();
+    let mut one_file_system = false;
+    let mut include_mounts = Vec::new();
+    let mut plan_options = PlanOptions::default();
+    let mut options = CopyOptions::default();
+    let mut skip_duplicates = false;
+    let mut failure_report = None;
+    let mut dry_run = false;
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| match args.next() {
+            Some(value) => value,
+            None => usage_error(&format!("Missing value for {}", name)),
+        };
+        match arg.as_str() {
+            "-s" | "--source" => source = Some(PathBuf::from(value(&arg))),
+            "-o" | "--output" => output = Some(PathBuf::from(value(&arg))),
+            "-e" | "--extensions" => {
+                filter.extensions = value(&arg)
+                    .split(',')
+                    .map(|extension| extension.trim().to_lowercase())
+                    .filter(|extension| !extension.is_empty())
+                    .collect()
+            }
+            "--min-size" => filter.min_size = Some(size_value(&value(&arg))),
+            "--max-size" => filter.max_size = Some(size_value(&value(&arg))),
+            "--modified-from" => filter.modified_from = Some(date_value(&value(&arg))),
+            "--modified-to" => filter.modified_to = Some(date_value(&value(&arg))),
+            "-x" | "--exclude" => exclude.push(value(&arg)),
+            "--no-default-excludes" => default_excludes = false,
+            "--no-ignore-files" => use_ignore_files = false,
+            "--detect-content" => detect_content = true,
+            "-j" | "--threads" => threads = thread_count(&value(&arg)),
+            "--symlinks" => {
+                let policy = value(&arg);
+                symlinks = SymlinkPolicy::from_name(&policy).unwrap_or_else(|| {
+                    usage_error(&format!("Unknown symbolic link policy '{}'", policy))
+                });
+            }
+            "-X" | "--one-file-system" => one_file_system = true,
+            "--include-mount" => include_mounts.push(PathBuf::from(value(&arg))),
+            "--fix-extensions" => plan_options.fix_extensions = true,
+            "--by-date" => {
+                plan_options.template = Some(PathTemplate::parse(DEFAULT_TEMPLATE).unwrap())
+            }
+            "-t" | "--template" => {
+                let template = value(&arg);
+                plan_options.template = Some(
+                    PathTemplate::parse(&template).unwrap_or_else(|message| usage_error(&message)),
+                );
+            }
+            "-c" | "--on-conflict" => {
+                let policy = value(&arg);
+                options.conflict_policy = ConflictPolicy::from_name(&policy)
+                    .unwrap_or_else(|| usage_error(&format!("Unknown policy '{}'", policy)));
+            }
+            "-d" | "--skip-duplicates" => skip_duplicates = true,
+            "-r" | "--failure-report" => failure_report = Some(PathBuf::from(value(&arg))),
+            "--verify" => options.verify = true,
+            "--manifest" => options.write_manifest = true,
+            "--mode" => {
+                let mode = value(&arg);
+                options.mode = TransferMode::from_name(&mode)
+                    .unwrap_or_else(|| usage_error(&format!("Unknown mode '{}'", mode)));
+            }
+            "-m" | "--move" => options.mode = TransferMode::Move,
+            "--no-preserve-times" => options.preserve_times = false,
+            "--preserve-permissions" => options.preserve_permissions = true,
+            "--preserve-xattrs" => options.preserve_xattrs = true,
+            "--copy-threads" => options.threads = thread_count(&value(&arg)),
+            "-n" | "--dry-run" => dry_run = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(EXIT_SUCCESS);
+            }
+            _ => usage_error(&format!("Unknown argument '{}'", arg)),
+        }
+    }
+
+    if default_excludes {
+        let defaults = DEFAULT_EXCLUDE_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string());
+        exclude.splice(0..0, defaults);
+    }
+
+    Arguments {
+        source: source.unwrap_or_else(|| usage_error("Missing --source")),
+        output: output.unwrap_or_else(|| usage_error("Missing --output")),
+        filter,
+        scan_options: ScanOptions {
+            exclude,
+            use_ignore_files,
+            detect_content,
+            threads,
+            symlinks,
+            one_file_system,
+            include_mounts,
+        },
+        plan_options,
+        options,
+        skip_duplicates,
+        failure_report,
+        dry_run,
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(EXIT_USAGE);
+}
+
+fn size_value(size: &str) -> u64 {
+    parse_size(size).unwrap_or_else(|| usage_error(&format!("Invalid size '{}'", size)))
+}
+
+fn thread_count(count: &str) -> usize {
+    count
+        .parse()
+        .unwrap_or_else(|_| usage_error(&format!("Invalid thread count '{}'", count)))
+}
+
+fn date_value(date: &str) -> NaiveDate {
+    parse_date(date).unwrap_or_else(|| usage_error(&format!("Invalid date '{}'", date)))
+}
+
+/// Matches `include` paths to mount points inside `source` in the form
+/// they are met while scanning. Prints mount points left out.
+fn included_mounts(source: &Path, include: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mounts = match mount_points(source) {
+        Ok(mounts) => mounts,
+        Err(err) => return Err(format!("Failed to list mount points: {}", err)),
+    };
+    let canonical = |path: &Path| fs::canonicalize(path).ok();
+    let mut included = Vec::new();
+    for path in include {
+        let target = canonical(path);
+        match mounts
+            .iter()
+            .find(|mount| target.is_some() && canonical(&mount.path) == target)
+        {
+            Some(mount) => included.push(mount.path.clone()),
+            None => {
+                return Err(format!(
+                    "'{}' is not a mount point inside the source directory",
+                    path.to_string_lossy()
+                ))
+            }
+        }
+    }
+    for mount in mounts
+        .iter()
+        .filter(|mount| !included.contains(&mount.path))
+    {
+        eprintln!(
+            "Skipping {} ({} {})",
+            mount.path.to_string_lossy(),
+            mount.file_system,
+            mount.source
+        );
+    }
+    Ok(included)
+}
+
+/// Scans source directory and copies found memories without user interaction.
+/// Returns process exit code.
+pub fn run(args: Arguments) -> i32 {
+    let Arguments {
+        source,
+        output,
+        filter,
+        scan_options,
+        plan_options,
+        options,
+        skip_duplicates,
+        failure_report,
+        dry_run,
+    } = args;
+    let mut scan_options = scan_options;
+
+    if scan_options.one_file_system {
+        match included_mounts(&source, &scan_options.include_mounts) {
+            Ok(include_mounts) => scan_options.include_mounts = include_mounts,
+            Err(message) => {
+                eprintln!("{}", message);
+                return EXIT_USAGE;
+            }
+        }
+    }
+
+    eprintln!("Scanning {}", source.to_string_lossy());
+    let scanned = AtomicU64::new(0);
+    let files = retrieve_files_recursively(
+        &source,
+        &scan_options,
+        &CancelToken::new(),
+        &|file: &Path, info: &FileInfo| filter.matches(file, info),
+        &|_file_path: &Path| {
+            scanned.fetch_add(1, Ordering::Relaxed);
+        },
+    );
+    eprintln!("Scanned {} files", scanned.into_inner());
+
+    let scan = match files {
+        Ok(scan) => scan,
+        Err(err) => {
+            eprintln!("Failed to parse directories due to Error:\n{}", err);
+            return EXIT_SCAN_FAILED;
+        }
+    };
+    if !scan.errors.is_empty() {
+        eprint!(
+            "{} locations could not be read:\n{}",
+            scan.errors.len(),
+            scan.error_report()
+        );
+    }
+    let mut files = match scan.files {
+        Some(files) => files,
+        None if !scan.errors.is_empty() => return EXIT_SCAN_FAILED,
+        None => {
+            eprintln!("No files with memories found!");
+            return EXIT_NOTHING_FOUND;
+        }
+    };
+
+    if skip_duplicates {
+        eprintln!("Looking for duplicate files");
+        let groups = find_duplicates(&source, &files, &|_file_path: &Path| {});
+        if let FileSystemItem::Directory(root) = &mut files {
+            for group in &groups {
+                for duplicate in &group.files[1..] {
+                    root.remove(duplicate);
+                }
+            }
+        }
+        eprintln!(
+            "Skipping {} duplicate files",
+            groups
+                .iter()
+                .map(|group| group.files.len() - 1)
+                .sum::<usize>()
+        );
+    }
+
+    let success = if scan.errors.is_empty() {
+        EXIT_SUCCESS
+    } else {
+        EXIT_INCOMPLETE_SCAN
+    };
+
+    let plan = plan_copy(
+        &source,
+        &output,
+        &files,
+        &plan_options,
+        &CancelToken::new(),
+        &|_file_path: &Path| {},
+    );
+    if dry_run {
+        println!("{}", plan);
+        return success;
+    }
+    eprintln!("{}", plan.summary());
+    if plan.collisions().next().is_some() {
+        eprintln!(
+            "Files would be copied to the same path, add {{counter}} or {{hash8}} to the template:"
+        );
+        for file in plan.collisions() {
+            eprintln!(
+                "  {} -> {}",
+                file.source.to_string_lossy(),
+                file.destination.to_string_lossy()
+            );
+        }
+        return EXIT_COLLISIONS;
+    }
+
+    let summary = copy_files(
+        &plan,
+        &options,
+        &CancelToken::new(),
+        &|progress: &CopyProgress| {
+            if progress.file_bytes_done == 0 {
+                eprintln!(
+                    "[{}/{}] Copying {}",
+                    progress.files_done + 1,
+                    progress.files_total,
+                    progress.file.to_string_lossy()
+                )
+            }
+        },
+    );
+    eprintln!("{}", summary);
+    if summary.failures.is_empty() {
+        return success;
+    }
+
+    eprint!("Failed to copy:\n{}", summary.failure_report());
+    if let Some(report_path) = failure_report {
+        if let Err(err) = fs::write(&report_path, summary.failure_report()) {
+            eprintln!(
+                "Failed to save report to {} due to Error:\n{}",
+                report_path.to_string_lossy(),
+                err
+            );
+        }
+    }
+    EXIT_COPY_FAILED
+}
@@ -0,0 +1,1265 @@
+use collect_memories::{
+    copy_files, find_duplicates, format_date, format_size, mount_points, parse_date, parse_size,
+    plan_copy, retrieve_files_recursively, reverse_file_paths, CancelToken, ConflictPolicy,
+    CopyOptions, CopyPlan, CopyProgress, CopySummary, DuplicateGroup, Error, FileFilter, FileInfo,
+    FileSystemItem, PathTemplate, PlanOptions, ReversePath, ScanOptions, ScanResult, SymlinkPolicy,
+    TransferMode, DEFAULT_EXCLUDE_PATTERNS, DEFAULT_TEMPLATE, IGNORE_FILE_NAME, MANIFEST_FILE_NAME,
+};
+use cursive::align::*;
+use cursive::event::*;
+use cursive::theme::*;
+use cursive::traits::*;
+use cursive::utils::Counter;
+use cursive::views::*;
+use cursive::{CbSink, Cursive};
+use cursive_tree_view::{Placement, TreeView};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod headless;
+
+fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().is_some() {
+        let args = headless::parse_arguments(args);
+        std::process::exit(headless::run(args));
+    }
+
+    let mut siv = setup_main_ui();
+    siv.run();
+}
+
+static DEFAULT_EXTENSIONS: &[&str; 10] = &[
+    "jpeg", "jpg", "bmp", "gif", "png", "avi", "mp4", "mpg", "mpeg", "wmv",
+];
+
+fn setup_main_ui() -> Cursive {
+    let mut s = Cursive::default();
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(DummyView);
+    layout.add_child(TextView::new("List of file extensions with memories:"));
+
+    let mut extension_list = SelectView::<String>::new();
+    extension_list.add_all_str(DEFAULT_EXTENSIONS.iter().copied());
+
+    let extension_list = extension_list
+        .with_name("extension_list")
+        .min_width(16)
+        .min_height(8);
+
+    let buttons = LinearLayout::vertical()
+        .child(DummyView)
+        .child(Button::new("Add", |s: &mut Cursive| {
+            add_list_item(s, "extension_list", "Enter a new extension")
+        }))
+        .child(Button::new("Remove", |s: &mut Cursive| {
+            delete_list_item(s, "extension_list")
+        }));
+
+    let mut exclude_list = SelectView::<String>::new();
+    exclude_list.add_all_str(DEFAULT_EXCLUDE_PATTERNS.iter().copied());
+
+    let exclude_list = exclude_list
+        .with_name("exclude_list")
+        .min_width(24)
+        .min_height(8)
+        .scrollable();
+
+    let exclude_buttons = LinearLayout::vertical()
+        .child(DummyView)
+        .child(Button::new("Add", |s: &mut Cursive| {
+            add_list_item(s, "exclude_list", "Enter a new exclude pattern")
+        }))
+        .child(Button::new("Remove", |s: &mut Cursive| {
+            delete_list_item(s, "exclude_list")
+        }));
+
+    let filters = LinearLayout::vertical()
+        .child(TextView::new("File size (e.g. 20K, 4M):"))
+        .child(
+            ListView::new()
+                .child("Min", EditView::new().with_name("min_size").fixed_width(12))
+                .child("Max", EditView::new().with_name("max_size").fixed_width(12)),
+        )
+        .child(DummyView)
+        .child(TextView::new("Modified (YYYY-MM-DD):"))
+        .child(
+            ListView::new()
+                .child(
+                    "From",
+                    EditView::new().with_name("modified_from").fixed_width(12),
+                )
+                .child(
+                    "To",
+                    EditView::new().with_name("modified_to").fixed_width(12),
+                ),
+        );
+
+    let extension_layout = LinearLayout::vertical().child(
+        LinearLayout::horizontal()
+            .child(Panel::new(extension_list))
+            .child(buttons)
+            .child(DummyView.fixed_width(2))
+            .child(filters),
+    );
+
+    layout.add_child(extension_layout);
+    layout.add_child(TextView::new(
+        "Skipped files and directories (.gitignore syntax):",
+    ));
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Panel::new(exclude_list))
+            .child(exclude_buttons),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().checked().with_name("ignore_files_checkbox"))
+            .child(TextView::new(format!(" Honor {} files", IGNORE_FILE_NAME))),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().with_name("detect_content_checkbox"))
+            .child(TextView::new(
+                " Detect memories by file content, also without or with wrong extension",
+            )),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().with_name("one_file_system_checkbox"))
+            .child(TextView::new(
+                " Stay on one file system, choose which mounted drives to scan",
+            )),
+    );
+    let mut symlink_policy = SelectView::<SymlinkPolicy>::new().popup();
+    for policy in SymlinkPolicy::ALL.iter() {
+        symlink_policy.add_item(policy.to_string(), *policy);
+    }
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(TextView::new("Symbolic links: "))
+            .child(symlink_policy.with_name("symlink_policy")),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(TextView::new("Scanning threads (empty for one per CPU): "))
+            .child(EditView::new().with_name("scan_threads").fixed_width(4)),
+    );
+    layout.add_child(TextView::new("Path to directory:"));
+    layout.add_child(
+        LinearLayout::vertical()
+            .child(
+                EditView::new()
+                    .content(".")
+                    .with_name("input_dir_path")
+                    .min_width(30),
+            )
+            .child(Button::new("Pick directory", |s: &mut Cursive| {
+                pick_directory(s, "input_dir_path")
+            })),
+    );
+
+    layout.add_child(DummyView);
+    layout.add_child(
+        TextView::new(
+            "Files are found by case insensitive extension matching.\n\
+             Empty size and date limits are not applied.",
+        )
+        .effect(Effect::Italic),
+    );
+
+    s.add_layer(
+        Dialog::around(layout)
+            .h_align(HAlign::Center)
+            .title("Collect memories")
+            .button("Scan for memories", scan_items_ui)
+            .button("Quit", |s: &mut Cursive| s.quit()),
+    );
+
+    s
+}
+
+fn pick_directory(s: &mut Cursive, path_name: &str) {
+    let result = nfd::open_pick_folder(None).unwrap();
+
+    s.clear();
+    s.refresh();
+    if let nfd::Response::Okay(file_path) = result {
+        let mut dir_path_view: ViewRef<EditView> = s.find_name(path_name).unwrap();
+        dir_path_view.set_content(file_path);
+    }
+}
+
+fn add_list_item(s: &mut Cursive, list_name: &'static str, title: &str) {
+    let ok = move |s: &mut Cursive, item: &str| {
+        let item = item.trim();
+        if !item.is_empty() {
+            s.call_on_name(list_name, |view: &mut SelectView| {
+                view.add_item_str(item);
+            });
+        }
+        s.pop_layer();
+    };
+
+    s.add_layer(
+        Dialog::around(
+            EditView::new()
+                .on_submit(ok)
+                .with_name("txt_list_item")
+                .fixed_width(20),
+        )
+        .title(title)
+        .button("Ok", move |s: &mut Cursive| {
+            let item = s
+                .call_on_name("txt_list_item", |view: &mut EditView| view.get_content())
+                .unwrap();
+            ok(s, item.as_ref())
+        })
+        .button("Cancel", |s: &mut Cursive| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn delete_list_item(s: &mut Cursive, list_name: &str) {
+    let mut select = s.find_name::<SelectView<String>>(list_name).unwrap();
+    match select.selected_id() {
+        None => s.add_layer(Dialog::info("No item to remove from the list!")),
+        Some(focus) => {
+            select.remove_item(focus);
+        }
+    }
+}
+
+fn list_items(s: &mut Cursive, list_name: &str) -> Vec<String> {
+    s.find_name::<SelectView<String>>(list_name)
+        .unwrap()
+        .iter()
+        .map(|(_, item)| item.clone())
+        .collect()
+}
+
+fn scan_items_ui(s: &mut Cursive) {
+    let input_path = PathBuf::from(
+        s.find_name::<EditView>("input_dir_path")
+            .unwrap()
+            .get_content()
+            .as_ref(),
+    );
+    let filter = match read_filter(s) {
+        Ok(filter) => filter,
+        Err(message) => {
+            s.add_layer(Dialog::info(message));
+            return;
+        }
+    };
+    let threads = match read_thread_count(s, "scan_threads") {
+        Ok(threads) => threads,
+        Err(message) => {
+            s.add_layer(Dialog::info(message));
+            return;
+        }
+    };
+    let scan_options = ScanOptions {
+        exclude: list_items(s, "exclude_list"),
+        use_ignore_files: is_checked(s, "ignore_files_checkbox"),
+        detect_content: is_checked(s, "detect_content_checkbox"),
+        threads,
+        symlinks: *s
+            .find_name::<SelectView<SymlinkPolicy>>("symlink_policy")
+            .unwrap()
+            .selection()
+            .unwrap(),
+        one_file_system: is_checked(s, "one_file_system_checkbox"),
+        include_mounts: Vec::new(),
+    };
+
+    if scan_options.one_file_system {
+        select_mounts_ui(s, input_path, filter, scan_options);
+    } else {
+        start_scan_ui(s, input_path, filter, scan_options);
+    }
+}
+
+/// Lists file systems mounted inside `input_path`, letting user include some of them
+/// into scanning despite `ScanOptions::one_file_system`.
+fn select_mounts_ui(
+    s: &mut Cursive,
+    input_path: PathBuf,
+    filter: FileFilter,
+    scan_options: ScanOptions,
+) {
+    let mounts = match mount_points(&input_path) {
+        Ok(mounts) => mounts,
+        Err(err) => {
+            s.add_layer(Dialog::info(format!(
+                "Failed to list mounted drives: {}",
+                err
+            )));
+            return;
+        }
+    };
+    if mounts.is_empty() {
+        start_scan_ui(s, input_path, filter, scan_options);
+        return;
+    }
+
+    let mut list = LinearLayout::vertical();
+    for (index, mount) in mounts.iter().enumerate() {
+        list.add_child(
+            LinearLayout::horizontal()
+                .child(Checkbox::new().with_name(format!("mount_{}", index)))
+                .child(TextView::new(format!(
+                    " {}  ({} {})",
+                    mount.path.to_string_lossy(),
+                    mount.file_system,
+                    mount.source
+                ))),
+        );
+    }
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(
+            "Drives mounted inside the directory, checked ones are scanned too:",
+        ))
+        .child(DummyView)
+        .child(list.scrollable().max_height(16));
+
+    s.add_layer(
+        Dialog::around(layout)
+            .title("Mounted drives")
+            .button("Scan", move |s: &mut Cursive| {
+                let mut scan_options = scan_options.clone();
+                scan_options.include_mounts = mounts
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| is_checked(s, &format!("mount_{}", index)))
+                    .map(|(_, mount)| mount.path.clone())
+                    .collect();
+                s.pop_layer();
+                start_scan_ui(s, input_path.clone(), filter.clone(), scan_options);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+/// Replaces the first screen by scanning progress, listing found files when done.
+fn start_scan_ui(
+    s: &mut Cursive,
+    input_path: PathBuf,
+    filter: FileFilter,
+    scan_options: ScanOptions,
+) {
+    let cb = s.cb_sink().clone();
+
+    s.pop_layer();
+
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(TextView::new("Scanning..."));
+    layout.add_child(TextView::new("").with_name("scan_progress_count"));
+    layout.add_child(TextView::new("").with_name("scan_progress_file"));
+
+    let cancel = CancelToken::new();
+    let worker_cancel = cancel.clone();
+
+    // And we start the worker thread.
+    thread::spawn(move || {
+        let progress = scan_progress_callback(cb.clone());
+        let files = retrieve_files_recursively(
+            &input_path,
+            &scan_options,
+            &worker_cancel,
+            &|file: &Path, info: &FileInfo| filter.matches(file, info),
+            &progress,
+        );
+
+        let cancelled = worker_cancel.is_cancelled();
+        cb.send(Box::new(move |s: &mut Cursive| {
+            list_files_found(s, files, input_path, cancelled)
+        }))
+        .unwrap();
+    });
+
+    s.add_layer(cancellable_dialog(layout, cancel));
+
+    s.set_autorefresh(true);
+}
+
+/// Progress dialog with Cancel button, also activated by Esc, cancelling `cancel`.
+fn cancellable_dialog(layout: LinearLayout, cancel: CancelToken) -> impl View {
+    let esc_cancel = cancel.clone();
+    OnEventView::new(
+        Dialog::around(layout)
+            .title("Collect memories")
+            .button("Cancel", move |_s: &mut Cursive| cancel.cancel()),
+    )
+    .on_event(Key::Esc, move |_s: &mut Cursive| esc_cancel.cancel())
+}
+
+/// Progress bar resolution.
+const PROGRESS_STEPS: usize = 1000;
+
+/// Minimal time between progress updates, 30 per second.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(1000 / 30);
+
+/// Creates callback showing currently processed file in the named `TextView`,
+/// updates are throttled to 30 per second.
+fn progress_callback(cb: CbSink, view_name: &'static str) -> impl Fn(&Path) {
+    let last_update = RefCell::new(Instant::now());
+    move |file_path: &Path| {
+        if last_update.borrow().elapsed() <= PROGRESS_INTERVAL {
+            return;
+        }
+        last_update.replace(Instant::now());
+
+        let file_path = file_path.to_string_lossy().into_owned();
+        cb.send(Box::new(move |s: &mut Cursive| {
+            s.find_name::<TextView>(view_name)
+                .unwrap()
+                .set_content(file_path)
+        }))
+        .unwrap();
+    }
+}
+
+/// Creates callback showing count of files found and currently scanned file,
+/// updates are throttled to 30 per second.
+/// Safe to call from several scanning threads.
+fn scan_progress_callback(cb: CbSink) -> impl Fn(&Path) + Sync {
+    let last_update = Mutex::new(Instant::now());
+    let scanned = AtomicUsize::new(0);
+    move |file_path: &Path| {
+        let count = scanned.fetch_add(1, Ordering::Relaxed) + 1;
+        {
+            let mut last_update = last_update.lock().unwrap();
+            if last_update.elapsed() <= PROGRESS_INTERVAL {
+                return;
+            }
+            *last_update = Instant::now();
+        }
+
+        let count = format!("{} files scanned", count);
+        let file_path = file_path.to_string_lossy().into_owned();
+        cb.send(Box::new(move |s: &mut Cursive| {
+            s.call_on_name("scan_progress_count", |view: &mut TextView| {
+                view.set_content(count)
+            });
+            s.call_on_name("scan_progress_file", |view: &mut TextView| {
+                view.set_content(file_path)
+            });
+        }))
+        .unwrap();
+    }
+}
+
+/// Creates callback moving the progress bar of `counter` by copied bytes and
+/// showing file counts, throughput, estimated time left and the current file.
+/// Text updates are throttled to 30 per second. Safe to call from several copying threads.
+fn copy_progress_callback(cb: CbSink, counter: Counter) -> impl Fn(&CopyProgress) + Sync {
+    let started = Instant::now();
+    let last_update = Mutex::new(Instant::now());
+    move |progress: &CopyProgress| {
+        let steps =
+            match (progress.bytes_done * PROGRESS_STEPS as u64).checked_div(progress.bytes_total) {
+                Some(steps) => steps as usize,
+                None => progress.files_done * PROGRESS_STEPS / progress.files_total.max(1),
+            };
+        counter.set(steps);
+        {
+            let mut last_update = last_update.lock().unwrap();
+            if last_update.elapsed() <= PROGRESS_INTERVAL {
+                return;
+            }
+            *last_update = Instant::now();
+        }
+
+        let elapsed = started.elapsed().as_secs_f64();
+        // Skipped and resumed files take no time, they would inflate the speed.
+        let throughput = progress.bytes_transferred as f64 / elapsed;
+        let time_left = if throughput > 0.0 {
+            let bytes_left = progress.bytes_total.saturating_sub(progress.bytes_done);
+            format_duration(Duration::from_secs_f64(bytes_left as f64 / throughput))
+        } else {
+            "unknown".to_owned()
+        };
+        let stats = format!(
+            "Files: {} / {}\nSize: {} / {}\nSpeed: {}/s\nTime left: {}",
+            progress.files_done,
+            progress.files_total,
+            format_size(progress.bytes_done),
+            format_size(progress.bytes_total),
+            format_size(throughput as u64),
+            time_left
+        );
+        let file_path = progress.file.to_string_lossy().into_owned();
+        cb.send(Box::new(move |s: &mut Cursive| {
+            s.call_on_name("copy_progress_stats", |view: &mut TextView| {
+                view.set_content(stats)
+            });
+            s.call_on_name("copy_progress_file", |view: &mut TextView| {
+                view.set_content(file_path)
+            });
+        }))
+        .unwrap();
+    }
+}
+
+/// Formats duration rounded to seconds, e.g. `1 h 05 min` or `3 min 20 s`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{} h {:02} min", seconds / 3600, seconds / 60 % 60)
+    } else if seconds >= 60 {
+        format!("{} min {:02} s", seconds / 60, seconds % 60)
+    } else {
+        format!("{} s", seconds)
+    }
+}
+
+/// Thread count from the named `EditView`, 0 when empty.
+fn read_thread_count(s: &mut Cursive, view_name: &str) -> Result<usize, String> {
+    let content = s.find_name::<EditView>(view_name).unwrap().get_content();
+    match content.trim() {
+        "" => Ok(0),
+        count => count
+            .parse()
+            .map_err(|_| format!("Invalid thread count '{}'!", count)),
+    }
+}
+
+/// Collects file filter from the first screen.
+fn read_filter(s: &mut Cursive) -> Result<FileFilter, String> {
+    let extensions: HashSet<String> = s
+        .find_name::<SelectView<String>>("extension_list")
+        .unwrap()
+        .iter()
+        .map(|(_, value)| value.trim().to_lowercase())
+        .collect();
+    let mut filter = FileFilter::new(extensions);
+
+    let mut field = |name: &str| -> Option<String> {
+        let content = s.find_name::<EditView>(name).unwrap().get_content();
+        Some(content.trim().to_owned()).filter(|content| !content.is_empty())
+    };
+    if let Some(size) = field("min_size") {
+        filter.min_size = Some(parse_size(&size).ok_or(format!("Invalid size '{}'!", size))?);
+    }
+    if let Some(size) = field("max_size") {
+        filter.max_size = Some(parse_size(&size).ok_or(format!("Invalid size '{}'!", size))?);
+    }
+    if let Some(date) = field("modified_from") {
+        filter.modified_from = Some(parse_date(&date).ok_or(format!("Invalid date '{}'!", date))?);
+    }
+    if let Some(date) = field("modified_to") {
+        filter.modified_to = Some(parse_date(&date).ok_or(format!("Invalid date '{}'!", date))?);
+    }
+    Ok(filter)
+}
+
+fn list_files_found(
+    s: &mut Cursive,
+    scan: Result<ScanResult, Error>,
+    input_path: PathBuf,
+    cancelled: bool,
+) {
+    s.pop_layer();
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(DummyView);
+    if cancelled {
+        layout.add_child(TextView::new(
+            "Scanning was cancelled, only memories found so far are listed.",
+        ));
+    }
+
+    match scan {
+        Ok(scan) => {
+            if !scan.errors.is_empty() {
+                layout.add_child(TextView::new(format!(
+                    "{} locations could not be read:",
+                    scan.errors.len()
+                )));
+                layout.add_child(Panel::new(
+                    TextView::new(scan.error_report())
+                        .scrollable()
+                        .max_height(6),
+                ));
+            }
+            match scan.files {
+                Some(files) => match generate_tree_view(&files) {
+                    Ok(tree_view) => {
+                        layout.add_child(DummyView);
+                        layout.add_child(TextView::new("Memories found:"));
+                        tree_edit_part(s, &mut layout, tree_view, input_path);
+                    }
+                    Err(err) => {
+                        layout.add_child(TextView::new("Failed to list memories due to Error:"));
+                        layout.add_child(TextView::new(err.to_string()));
+                    }
+                },
+                None => {
+                    layout.add_child(TextView::new("No files with memories found!"));
+                }
+            }
+        }
+        Err(err) => {
+            layout.add_child(TextView::new("Failed to parse directories due to Error:"));
+            layout.add_child(TextView::new(format!("{}", err)));
+        }
+    };
+
+    s.add_layer(
+        Dialog::around(layout)
+            .h_align(HAlign::Center)
+            .title("Collect memories")
+            .button("Quit", |s: &mut Cursive| s.quit()),
+    );
+}
+
+fn remove_active_subtree(s: &mut Cursive) {
+    let mut tree_view = match s.find_name::<TreeView<TreeViewItem>>("tree_view") {
+        Some(x) => x,
+        None => return,
+    };
+    if let Some(row) = tree_view.row() {
+        remove_tree_row(&mut tree_view, row);
+    }
+}
+
+/// Removes item at `row` with all its children
+/// and subtracts removed files from totals of parent directories.
+fn remove_tree_row(tree_view: &mut TreeView<TreeViewItem>, row: usize) {
+    let (file_count, total_size) = match tree_view.borrow_item(row) {
+        Some(item) => (item.file_count, item.total_size),
+        None => return,
+    };
+    let mut parent = tree_view.item_parent(row);
+    while let Some(parent_row) = parent {
+        if let Some(item) = tree_view.borrow_item_mut(parent_row) {
+            item.file_count -= file_count;
+            item.total_size -= total_size;
+        }
+        parent = tree_view.item_parent(parent_row);
+    }
+    tree_view.remove_item(row);
+}
+
+fn tree_edit_part(
+    s: &mut Cursive,
+    layout: &mut LinearLayout,
+    tree_view: TreeView<TreeViewItem>,
+    input_path: PathBuf,
+) {
+    s.add_global_callback(Event::Char('r'), remove_active_subtree);
+    let tree_view = tree_view.with_name("tree_view");
+
+    layout.add_child(
+        LinearLayout::horizontal().child(tree_view).child(
+            LinearLayout::vertical()
+                .child(DummyView)
+                .child(Button::new(
+                    "Remove selected subtree from found file list!",
+                    remove_active_subtree,
+                ))
+                .child(TextView::new("Shortcut: Press r"))
+                .child(DummyView)
+                .child(Button::new("Find duplicate files", {
+                    let input_path = input_path.clone();
+                    move |s: &mut Cursive| find_duplicates_ui(s, input_path.clone())
+                }))
+                .child(DummyView)
+                .child(TextView::new("Path to output directory:"))
+                .child(
+                    LinearLayout::vertical()
+                        .child(
+                            EditView::new()
+                                .content(".")
+                                .with_name("output_dir_path")
+                                .min_width(30),
+                        )
+                        .child(Button::new("Pick directory", |s: &mut Cursive| {
+                            pick_directory(s, "output_dir_path")
+                        })),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(Checkbox::new().with_name("fix_extensions_checkbox"))
+                        .child(TextView::new(" Fix extensions of detected files")),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(Checkbox::new().with_name("template_checkbox"))
+                        .child(TextView::new(" Destination path template:")),
+                )
+                .child(
+                    EditView::new()
+                        .content(DEFAULT_TEMPLATE)
+                        .with_name("path_template")
+                        .min_width(30),
+                )
+                .child(
+                    TextView::new(
+                        "{year} {month} {day} {camera_model} {original_name}\n\
+                         {ext} {source_dir} {counter} {hash8}",
+                    )
+                    .effect(Effect::Italic),
+                )
+                .child(DummyView)
+                .child(Button::new("Copy memories", move |s: &mut Cursive| {
+                    let output_path = PathBuf::from(
+                        s.find_name::<EditView>("output_dir_path")
+                            .unwrap()
+                            .get_content()
+                            .as_ref(),
+                    );
+                    let template = if is_checked(s, "template_checkbox") {
+                        let template = s.find_name::<EditView>("path_template").unwrap();
+                        match PathTemplate::parse(&template.get_content()) {
+                            Ok(template) => Some(template),
+                            Err(message) => {
+                                s.add_layer(Dialog::info(message));
+                                return;
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    let options = PlanOptions {
+                        fix_extensions: is_checked(s, "fix_extensions_checkbox"),
+                        template,
+                    };
+                    plan_copy_ui(s, input_path.clone(), output_path, options);
+                })),
+        ),
+    );
+}
+
+fn find_duplicates_ui(s: &mut Cursive, input_path: PathBuf) {
+    let cb = s.cb_sink().clone();
+    let paths = tree_file_paths(&mut s.find_name("tree_view").unwrap());
+    let file_tree = match reverse_file_paths(&paths) {
+        Ok(file_tree) => file_tree,
+        Err(err) => {
+            s.add_layer(Dialog::info(err.to_string()));
+            return;
+        }
+    };
+
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(TextView::new("Looking for duplicate files..."));
+    layout.add_child(TextView::new("").with_name("duplicates_progress_file"));
+
+    thread::spawn(move || {
+        let groups = find_duplicates(
+            &input_path,
+            &FileSystemItem::Directory(file_tree),
+            &progress_callback(cb.clone(), "duplicates_progress_file"),
+        );
+
+        cb.send(Box::new(move |s: &mut Cursive| {
+            s.pop_layer();
+            list_duplicates_ui(s, groups)
+        }))
+        .unwrap();
+    });
+
+    s.add_layer(Dialog::around(layout).title("Collect memories"));
+
+    s.set_autorefresh(true);
+}
+
+fn list_duplicates_ui(s: &mut Cursive, groups: Vec<DuplicateGroup>) {
+    if groups.is_empty() {
+        s.add_layer(Dialog::info("No duplicate files found!"));
+        return;
+    }
+
+    let mut group_of_file = HashMap::new();
+    for (idx, group) in groups.iter().enumerate() {
+        for file in &group.files {
+            group_of_file.insert(file.clone(), idx + 1);
+        }
+    }
+    let mut tree_view = s.find_name::<TreeView<TreeViewItem>>("tree_view").unwrap();
+    for row in 0..tree_view.len() {
+        if let Some(item) = tree_view.borrow_item_mut(row) {
+            item.duplicate_group = group_of_file.get(&item.path.path()).copied();
+        }
+    }
+
+    let mut group_list = LinearLayout::vertical();
+    for (idx, group) in groups.iter().enumerate() {
+        let mut select = SelectView::<PathBuf>::new();
+        for file in &group.files {
+            select.add_item(file.to_string_lossy().into_owned(), file.clone());
+        }
+        group_list.add_child(
+            Panel::new(select.with_name(format!("duplicate_group_{}", idx + 1))).title(format!(
+                "Duplicate #{}: {} copies, {} each",
+                idx + 1,
+                group.files.len(),
+                format_size(group.size)
+            )),
+        );
+    }
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(format!(
+            "{} groups of duplicate files found. Select a copy to keep in each group:",
+            groups.len()
+        )))
+        .child(group_list.scrollable().max_height(20));
+
+    s.add_layer(
+        Dialog::around(layout)
+            .title("Collect memories")
+            .button("Keep selected copies", move |s: &mut Cursive| {
+                let mut removed = HashSet::new();
+                for (idx, group) in groups.iter().enumerate() {
+                    let kept = s
+                        .find_name::<SelectView<PathBuf>>(&format!("duplicate_group_{}", idx + 1))
+                        .unwrap()
+                        .selection();
+                    for file in &group.files {
+                        if Some(file) != kept.as_deref() {
+                            removed.insert(file.clone());
+                        }
+                    }
+                }
+                s.pop_layer();
+                remove_tree_files(&mut s.find_name("tree_view").unwrap(), &removed);
+            })
+            .dismiss_button("Close"),
+    );
+}
+
+/// Removes files with the given paths from the tree view.
+fn remove_tree_files(tree_view: &mut TreeView<TreeViewItem>, removed: &HashSet<PathBuf>) {
+    for row in (0..tree_view.len()).rev() {
+        let remove = match tree_view.borrow_item(row) {
+            Some(item) => item.info.is_some() && removed.contains(&item.path.path()),
+            None => false,
+        };
+        if remove {
+            remove_tree_row(tree_view, row);
+        }
+    }
+}
+
+/// Returns paths and metadata of all files left in the tree view.
+fn tree_file_paths(tree_view: &mut TreeView<TreeViewItem>) -> Vec<(ReversePath, FileInfo)> {
+    // Expand collapsed directories so that every item occupies a row.
+    let mut row = 0;
+    while row < tree_view.len() {
+        tree_view.expand_item(row);
+        row += 1;
+    }
+    (0..tree_view.len())
+        .filter_map(|row| tree_view.borrow_item(row))
+        .filter_map(|item| Some((item.path.clone(), item.info.clone()?)))
+        .collect()
+}
+
+fn plan_copy_ui(s: &mut Cursive, input_path: PathBuf, output_path: PathBuf, options: PlanOptions) {
+    let cb = s.cb_sink().clone();
+    let paths = tree_file_paths(&mut s.find_name("tree_view").unwrap());
+    let file_tree = match reverse_file_paths(&paths) {
+        Ok(file_tree) => file_tree,
+        Err(err) => {
+            s.add_layer(Dialog::info(err.to_string()));
+            return;
+        }
+    };
+
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(TextView::new("Planning..."));
+    layout.add_child(TextView::new("").with_name("plan_progress_file"));
+
+    let cancel = CancelToken::new();
+    let worker_cancel = cancel.clone();
+
+    thread::spawn(move || {
+        let plan = plan_copy(
+            &input_path,
+            &output_path,
+            &FileSystemItem::Directory(file_tree),
+            &options,
+            &worker_cancel,
+            &progress_callback(cb.clone(), "plan_progress_file"),
+        );
+
+        let cancelled = worker_cancel.is_cancelled();
+        cb.send(Box::new(move |s: &mut Cursive| {
+            s.pop_layer();
+            if !cancelled {
+                confirm_copy_ui(s, plan);
+            }
+        }))
+        .unwrap();
+    });
+
+    s.add_layer(cancellable_dialog(layout, cancel));
+}
+
+fn confirm_copy_ui(s: &mut Cursive, plan: CopyPlan) {
+    let mut layout = LinearLayout::vertical()
+        .child(TextView::new(
+            "Do you really want to copy found files to the following path?",
+        ))
+        .child(TextView::new(format!(
+            "To: {}",
+            plan.output_path.to_string_lossy().into_owned()
+        )))
+        .child(DummyView)
+        .child(TextView::new(plan.summary()));
+    layout.add_child(Panel::new(
+        TextView::new(plan.to_string()).scrollable().max_height(15),
+    ));
+    if plan.collisions().next().is_some() {
+        layout.add_child(
+            TextView::new(
+                "Some files would be copied to the same path! Add {counter} or {hash8}\n\
+                 to the template, otherwise only the first of them is copied as is.",
+            )
+            .effect(Effect::Bold),
+        );
+    }
+
+    layout.add_child(TextView::new("When destination file already exists:"));
+    let mut conflict_group = RadioGroup::new();
+    for policy in ConflictPolicy::ALL.iter() {
+        let button = conflict_group.button(*policy, policy.to_string());
+        layout.add_child(if *policy == ConflictPolicy::default() {
+            button.selected()
+        } else {
+            button
+        });
+    }
+
+    layout.add_child(DummyView);
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().with_name("verify_checkbox"))
+            .child(TextView::new(" Verify copied files by SHA-256 checksum")),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().with_name("manifest_checkbox"))
+            .child(TextView::new(format!(
+                " Write checksum manifest {} to output directory",
+                MANIFEST_FILE_NAME
+            ))),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(
+                Checkbox::new()
+                    .checked()
+                    .with_name("preserve_times_checkbox"),
+            )
+            .child(TextView::new(" Keep modification and access times")),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().with_name("preserve_permissions_checkbox"))
+            .child(TextView::new(" Keep permissions")),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(Checkbox::new().with_name("preserve_xattrs_checkbox"))
+            .child(TextView::new(" Keep extended attributes")),
+    );
+    layout.add_child(
+        LinearLayout::horizontal()
+            .child(TextView::new(
+                "Files copied at once (empty for one per CPU): ",
+            ))
+            .child(
+                EditView::new()
+                    .content(CopyOptions::default().threads.to_string())
+                    .with_name("copy_threads")
+                    .fixed_width(4),
+            ),
+    );
+
+    layout.add_child(DummyView);
+    let mut mode_group = RadioGroup::new();
+    for mode in TransferMode::ALL.iter() {
+        let button = mode_group.button(*mode, mode.to_string());
+        layout.add_child(if *mode == TransferMode::default() {
+            button.selected()
+        } else {
+            button
+        });
+    }
+
+    s.add_layer(
+        Dialog::around(layout)
+            .button("Ok", move |s: &mut Cursive| {
+                let threads = match read_thread_count(s, "copy_threads") {
+                    Ok(threads) => threads,
+                    Err(message) => {
+                        s.add_layer(Dialog::info(message));
+                        return;
+                    }
+                };
+                let options = CopyOptions {
+                    conflict_policy: *conflict_group.selection(),
+                    verify: is_checked(s, "verify_checkbox"),
+                    write_manifest: is_checked(s, "manifest_checkbox"),
+                    mode: *mode_group.selection(),
+                    preserve_times: is_checked(s, "preserve_times_checkbox"),
+                    preserve_permissions: is_checked(s, "preserve_permissions_checkbox"),
+                    preserve_xattrs: is_checked(s, "preserve_xattrs_checkbox"),
+                    threads,
+                };
+                if options.mode == TransferMode::Move {
+                    confirm_move_ui(s, plan.clone(), options);
+                } else {
+                    s.pop_layer();
+                    copy_items_ui(s, plan.clone(), options);
+                }
+            })
+            .dismiss_button("Cancel")
+            .title("Collect memories"),
+    );
+}
+
+fn confirm_move_ui(s: &mut Cursive, plan: CopyPlan, options: CopyOptions) {
+    let message = format!(
+        "{} files will be removed from\n{}\nafter they are transferred. \
+         Empty source directories will be removed too.\n\
+         Files copied to another disk are verified by checksum before removal.",
+        plan.files.len(),
+        plan.input_path.to_string_lossy()
+    );
+    s.add_layer(
+        Dialog::text(message)
+            .title("Move memories?")
+            .button("Move", move |s: &mut Cursive| {
+                s.pop_layer();
+                s.pop_layer();
+                copy_items_ui(s, plan.clone(), options.clone());
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+fn is_checked(s: &mut Cursive, name: &str) -> bool {
+    s.find_name::<Checkbox>(name).unwrap().is_checked()
+}
+
+fn insert_tree_view(
+    reverse_path: &ReversePath,
+    location: &FileSystemItem,
+    tree: &mut TreeView<TreeViewItem>,
+    placement_type: Placement,
+    parent_row: usize,
+) -> Result<(), Error> {
+    let (file_count, total_size) = location.totals();
+    let (info, symlink) = match location {
+        FileSystemItem::File(info) => (Some(info.clone()), info.symlink),
+        FileSystemItem::Directory(directory) => (None, directory.is_symlink()),
+    };
+    let row = tree
+        .insert_item(
+            TreeViewItem {
+                path: reverse_path.clone(),
+                info,
+                file_count,
+                total_size,
+                duplicate_group: None,
+                symlink,
+            },
+            placement_type,
+            parent_row,
+        )
+        .ok_or_else(|| Error::TreeInsertion(reverse_path.path()))?;
+    match location {
+        FileSystemItem::File(_) => {}
+        FileSystemItem::Directory(root_dir) => {
+            for (name, dir) in root_dir.content() {
+                insert_tree_view(
+                    &ReversePath::new_from_prefix(reverse_path, name),
+                    dir,
+                    tree,
+                    Placement::LastChild,
+                    row,
+                )?;
+            }
+        }
+    };
+    Ok(())
+}
+
+fn generate_tree_view(files: &FileSystemItem) -> Result<TreeView<TreeViewItem>, Error> {
+    let mut tree = TreeView::new();
+    if let FileSystemItem::Directory(root_dir) = files {
+        for (name, dir) in root_dir.content() {
+            insert_tree_view(&ReversePath::new(name), dir, &mut tree, Placement::After, 0)?;
+        }
+    }
+    Ok(tree)
+}
+
+#[derive(Debug)]
+struct TreeViewItem {
+    path: ReversePath,
+    /// File metadata, `None` for directories.
+    info: Option<FileInfo>,
+    /// Count and total size of files in the subtree.
+    file_count: u32,
+    total_size: u64,
+    /// Number of duplicate group the file belongs to.
+    duplicate_group: Option<usize>,
+    /// Item was reached through a symbolic link.
+    symlink: bool,
+}
+
+impl std::fmt::Display for TreeViewItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = self.path.last_member();
+        write!(f, "{}", name.to_string_lossy().as_ref())?;
+        match &self.info {
+            Some(info) => {
+                write!(f, "  ({}", format_size(info.size))?;
+                if let Some(modified) = info.modified {
+                    write!(f, ", {}", format_date(modified))?;
+                }
+                write!(f, ")")?;
+                if info.fixed_extension(Path::new(name)).is_some() {
+                    write!(f, " [{}]", info.media_type)?;
+                }
+            }
+            None => write!(
+                f,
+                "  ({} files, {})",
+                self.file_count,
+                format_size(self.total_size)
+            )?,
+        }
+        if let Some(group) = self.duplicate_group {
+            write!(f, " [duplicate #{}]", group)?;
+        }
+        if self.symlink {
+            write!(f, " [symlink]")?;
+        }
+        Ok(())
+    }
+}
+
+fn copy_items_ui(s: &mut Cursive, plan: CopyPlan, options: CopyOptions) {
+    let cb = s.cb_sink().clone();
+    s.pop_layer();
+
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(TextView::new(match options.mode {
+        TransferMode::Copy => "Copying...",
+        TransferMode::Move => "Moving...",
+        TransferMode::Hardlink | TransferMode::Reflink => "Linking...",
+    }));
+    layout.add_child(TextView::new(format!(
+        "From: {}",
+        &plan.input_path.to_string_lossy()
+    )));
+    layout.add_child(TextView::new(format!(
+        "To: {}",
+        &plan.output_path.to_string_lossy()
+    )));
+    let counter = Counter::new(0);
+    layout.add_child(DummyView);
+    layout.add_child(
+        ProgressBar::new()
+            .range(0, PROGRESS_STEPS)
+            .with_value(counter.clone()),
+    );
+    layout.add_child(TextView::new("").with_name("copy_progress_stats"));
+    layout.add_child(TextView::new("").with_name("copy_progress_file"));
+
+    let cancel = CancelToken::new();
+    let worker_cancel = cancel.clone();
+
+    // And we start the worker thread.
+    thread::spawn(move || {
+        let progress = copy_progress_callback(cb.clone(), counter);
+        let summary = copy_files(&plan, &options, &worker_cancel, &progress);
+
+        cb.send(Box::new(move |s: &mut Cursive| {
+            done_ui(s, plan, summary, options)
+        }))
+        .unwrap();
+    });
+
+    s.add_layer(cancellable_dialog(layout, cancel));
+
+    s.set_autorefresh(true);
+}
+
+fn done_ui(s: &mut Cursive, plan: CopyPlan, summary: CopySummary, options: CopyOptions) {
+    s.pop_layer();
+
+    let mut layout = LinearLayout::vertical()
+        .child(TextView::new(summary.to_string()))
+        .child(TextView::new(format!(
+            "From: {}",
+            plan.input_path.to_string_lossy()
+        )))
+        .child(TextView::new(format!(
+            "To: {}",
+            plan.output_path.to_string_lossy()
+        )));
+
+    let continue_options = options.clone();
+    let mut dialog = if summary.failures.is_empty() {
+        Dialog::around(layout)
+    } else {
+        let report = summary.failure_report();
+        layout.add_child(DummyView);
+        layout.add_child(TextView::new("Failed to copy:"));
+        layout.add_child(Panel::new(
+            TextView::new(report.clone()).scrollable().max_height(10),
+        ));
+        layout.add_child(TextView::new("Save failure report to:"));
+        layout.add_child(
+            EditView::new()
+                .content(
+                    plan.output_path
+                        .join("collect-memories-failures.txt")
+                        .to_string_lossy(),
+                )
+                .with_name("failure_report_path")
+                .min_width(30),
+        );
+
+        let retry_plan = plan.retry_plan(&summary);
+        Dialog::around(layout)
+            .button("Save report", move |s: &mut Cursive| {
+                let report_path = PathBuf::from(
+                    s.find_name::<EditView>("failure_report_path")
+                        .unwrap()
+                        .get_content()
+                        .as_ref(),
+                );
+                let message = match fs::write(&report_path, &report) {
+                    Ok(()) => format!("Report saved to {}", report_path.to_string_lossy()),
+                    Err(err) => format!("Failed to save report due to Error:\n{}", err),
+                };
+                s.add_layer(Dialog::info(message));
+            })
+            .button("Retry failed", move |s: &mut Cursive| {
+                copy_items_ui(s, retry_plan.clone(), options.clone());
+            })
+    };
+    if summary.cancelled > 0 {
+        // Files copied before cancelling are found in the journal and skipped.
+        dialog.add_button("Continue", move |s: &mut Cursive| {
+            copy_items_ui(s, plan.clone(), continue_options.clone());
+        });
+    }
+    dialog.set_title("Collect memories");
+    s.add_layer(dialog.button("Close", |s| s.quit()));
+}
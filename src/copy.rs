@@ -0,0 +1,1645 @@
+use crate::cancel::{is_cancelled_error, CancelToken};
+use crate::hash::{hash_file, hash_to_hex, FileHash};
+use crate::journal::{Journal, JOURNAL_FILE_NAME};
+use crate::metadata::{camera_model, capture_date};
+use crate::preserve::preserve_read_metadata;
+use crate::template::{PathTemplate, Placeholder, TemplateValues};
+use crate::transfer::{
+    remove_temporary_files, same_file, transfer_file, TransferMode, Transferred,
+};
+use crate::{format_size, Directory, FileInfo, FileSystemItem};
+use rayon::prelude::*;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Single file copy operation planned by `plan_copy`.
+#[derive(Debug, Clone)]
+pub struct FileCopy {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub size: u64,
+    /// Destination file already exists.
+    pub conflict: bool,
+    /// Another planned file has the same destination.
+    pub collision: bool,
+    /// Source was reached through a symbolic link, see `FileInfo::linked`.
+    pub linked: bool,
+}
+
+/// Everything `copy_files` is going to do, gathered without writing to the disk.
+#[derive(Debug, Default, Clone)]
+pub struct CopyPlan {
+    pub input_path: PathBuf,
+    pub output_path: PathBuf,
+    /// Directories to create, parents before children.
+    pub directories: Vec<PathBuf>,
+    /// Source directories mirrored by directories to create.
+    pub directory_sources: HashMap<PathBuf, PathBuf>,
+    pub files: Vec<FileCopy>,
+    pub total_bytes: u64,
+}
+
+impl CopyPlan {
+    pub fn conflicts(&self) -> impl Iterator<Item = &FileCopy> {
+        self.files.iter().filter(|file| file.conflict)
+    }
+
+    pub fn collisions(&self) -> impl Iterator<Item = &FileCopy> {
+        self.files.iter().filter(|file| file.collision)
+    }
+
+    /// Plan for copying files and creating directories that failed in `summary`.
+    pub fn retry_plan(&self, summary: &CopySummary) -> CopyPlan {
+        let mut plan = CopyPlan {
+            input_path: self.input_path.clone(),
+            output_path: self.output_path.clone(),
+            ..Default::default()
+        };
+        for failure in &summary.failures {
+            match &failure.file {
+                Some(file) => {
+                    let mut file = file.clone();
+                    file.conflict = file.destination.exists();
+                    plan.total_bytes += file.size;
+                    plan.files.push(file);
+                }
+                None => {
+                    if self.directories.contains(&failure.path) && !failure.path.exists() {
+                        plan.directories.push(failure.path.clone());
+                        if let Some(source) = self.directory_sources.get(&failure.path) {
+                            plan.directory_sources
+                                .insert(failure.path.clone(), source.clone());
+                        }
+                    }
+                }
+            }
+        }
+        plan
+    }
+
+    /// One line summary of the plan.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} directories to create, {} files to copy ({}), {} conflicts",
+            self.directories.len(),
+            self.files.len(),
+            format_size(self.total_bytes),
+            self.conflicts().count()
+        );
+        let collisions = self.collisions().count();
+        if collisions > 0 {
+            summary += &format!(", {} files with the same destination", collisions);
+        }
+        summary
+    }
+}
+
+impl fmt::Display for CopyPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Directories to create:")?;
+        for directory in &self.directories {
+            writeln!(f, "  {}", directory.to_string_lossy())?;
+        }
+        writeln!(f, "Files to copy:")?;
+        for file in &self.files {
+            write!(
+                f,
+                "  {} -> {} ({})",
+                file.source.to_string_lossy(),
+                file.destination.to_string_lossy(),
+                format_size(file.size)
+            )?;
+            if file.conflict {
+                write!(f, " [already exists]")?;
+            }
+            if file.collision {
+                write!(f, " [same destination as another file]")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{}", self.summary())
+    }
+}
+
+/// Settings deciding where files are copied to.
+#[derive(Debug, Default, Clone)]
+pub struct PlanOptions {
+    /// Give files with format detected from content the usual extension
+    /// of that format, e.g. `FILE0001.CHK` becomes `FILE0001.jpg`.
+    pub fix_extensions: bool,
+    /// Generate destination paths from the template instead of
+    /// mirroring source directories.
+    pub template: Option<PathTemplate>,
+}
+
+/// Gathers directories to create and files to copy from `item`
+/// into `output_path`, keeping directory structure intact
+/// unless `PlanOptions::template` is given.
+///
+/// Files mapped to the same destination are marked as `FileCopy::collision`.
+///
+/// `callback` is called for every file before it is planned, filling a template
+/// can read file metadata or content. When `cancel` is cancelled planning stops,
+/// the returned plan is incomplete then.
+pub fn plan_copy<F>(
+    input_path: &Path,
+    output_path: &Path,
+    item: &FileSystemItem,
+    options: &PlanOptions,
+    cancel: &CancelToken,
+    callback: &F,
+) -> CopyPlan
+where
+    F: Fn(&Path),
+{
+    let mut context = PlanContext {
+        options,
+        cancel,
+        callback,
+        destinations: HashMap::new(),
+        plan: CopyPlan {
+            input_path: input_path.to_owned(),
+            output_path: output_path.to_owned(),
+            ..Default::default()
+        },
+    };
+    if options.template.is_some() {
+        plan_directory(output_path, &mut context.plan);
+    }
+    context.plan_item(input_path, output_path, item);
+    let PlanContext {
+        mut plan,
+        destinations,
+        ..
+    } = context;
+    if options.template.is_some() {
+        plan.directories.sort();
+    }
+    for file in &mut plan.files {
+        file.collision = destinations[&file.destination] > 1;
+    }
+    plan
+}
+
+/// State of `plan_copy` while it walks the file tree.
+struct PlanContext<'a, F> {
+    options: &'a PlanOptions,
+    cancel: &'a CancelToken,
+    callback: &'a F,
+    /// Count of planned files by destination.
+    destinations: HashMap<PathBuf, usize>,
+    plan: CopyPlan,
+}
+
+impl<'a, F> PlanContext<'a, F>
+where
+    F: Fn(&Path),
+{
+    fn plan_item(&mut self, source_path: &Path, destination_path: &Path, item: &FileSystemItem) {
+        let options = self.options;
+        match item {
+            FileSystemItem::File(info) => {
+                if self.cancel.is_cancelled() {
+                    return;
+                }
+                (self.callback)(source_path);
+                let destination_path = match &options.template {
+                    Some(template) => {
+                        let destination_path = template_destination(
+                            template,
+                            source_path,
+                            info,
+                            destination_path,
+                            &self.destinations,
+                            &self.plan,
+                        );
+                        if let Some(directory) = destination_path.parent() {
+                            plan_directory(directory, &mut self.plan);
+                        }
+                        destination_path
+                    }
+                    None => destination_path.to_owned(),
+                };
+                *self
+                    .destinations
+                    .entry(destination_path.clone())
+                    .or_default() += 1;
+                let size = info.size;
+                self.plan.total_bytes += size;
+                self.plan.files.push(FileCopy {
+                    source: source_path.to_owned(),
+                    conflict: destination_path.exists(),
+                    destination: destination_path,
+                    size,
+                    collision: false,
+                    linked: info.linked,
+                });
+            }
+            FileSystemItem::Directory(directory) => {
+                if options.template.is_none() && !destination_path.exists() {
+                    self.plan.directories.push(destination_path.to_owned());
+                    self.plan
+                        .directory_sources
+                        .insert(destination_path.to_owned(), source_path.to_owned());
+                }
+                let mut children: Vec<_> = directory.content.iter().collect();
+                children.sort_by(|a, b| a.0.cmp(b.0));
+                let mut fixed_names = HashSet::new();
+                for (name, child_item) in children {
+                    let mut destination_name = name.to_owned();
+                    if let FileSystemItem::File(info) = child_item {
+                        let extension = info.fixed_extension(Path::new(name));
+                        if let Some(extension) = extension.filter(|_| options.fix_extensions) {
+                            destination_name = fixed_name(name, extension, directory, &fixed_names);
+                            fixed_names.insert(destination_name.clone());
+                        }
+                    }
+                    self.plan_item(
+                        &source_path.join(name),
+                        &destination_path.join(destination_name),
+                        child_item,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Fills `template` for a file, name and extension are taken from
+/// the mirrored destination, which has the fixed extension if requested.
+fn template_destination(
+    template: &PathTemplate,
+    source_path: &Path,
+    info: &FileInfo,
+    mirrored_destination: &Path,
+    destinations: &HashMap<PathBuf, usize>,
+    plan: &CopyPlan,
+) -> PathBuf {
+    let name = |part: Option<&OsStr>| part.unwrap_or_default().to_string_lossy().into_owned();
+    let date_used = [Placeholder::Year, Placeholder::Month, Placeholder::Day]
+        .iter()
+        .any(|placeholder| template.uses(*placeholder));
+    let source_dir = source_path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(&plan.input_path).ok())
+        .unwrap_or_else(|| Path::new(""));
+
+    let mut values = TemplateValues {
+        date: if date_used {
+            capture_date(source_path, info)
+        } else {
+            None
+        },
+        camera_model: if template.uses(Placeholder::CameraModel) {
+            camera_model(source_path, info)
+        } else {
+            None
+        },
+        original_name: name(mirrored_destination.file_stem()),
+        extension: name(mirrored_destination.extension()),
+        source_dir: source_dir.to_owned(),
+        counter: 1,
+        hash: if template.uses(Placeholder::Hash8) {
+            hash_file(source_path).ok().map(|hash| hash_to_hex(&hash))
+        } else {
+            None
+        },
+    };
+
+    let mut destination = plan.output_path.join(template.render(&values));
+    if template.uses(Placeholder::Counter) {
+        // Files of earlier runs keep their numbers too.
+        while destinations.contains_key(&destination) || destination.exists() {
+            values.counter += 1;
+            destination = plan.output_path.join(template.render(&values));
+        }
+    }
+    destination
+}
+
+/// Adds `directory` and its missing parents to directories to create.
+fn plan_directory(directory: &Path, plan: &mut CopyPlan) {
+    if directory.as_os_str().is_empty()
+        || directory.exists()
+        || plan.directories.iter().any(|planned| planned == directory)
+    {
+        return;
+    }
+    if let Some(parent) = directory.parent() {
+        plan_directory(parent, plan);
+    }
+    plan.directories.push(directory.to_owned());
+}
+
+/// Replaces extension of `name`, or appends `extension` to the whole name
+/// when that would collide with another file in the same directory.
+fn fixed_name(
+    name: &OsStr,
+    extension: &str,
+    directory: &Directory,
+    fixed_names: &HashSet<OsString>,
+) -> OsString {
+    let fixed = Path::new(name).with_extension(extension).into_os_string();
+    if directory.content.contains_key(&fixed) || fixed_names.contains(&fixed) {
+        let mut appended = name.to_owned();
+        appended.push(".");
+        appended.push(extension);
+        appended
+    } else {
+        fixed
+    }
+}
+
+/// What to do when destination file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep existing file, do not copy.
+    #[default]
+    Skip,
+    /// Replace existing file.
+    Overwrite,
+    /// Replace existing file only if source was modified later.
+    OverwriteIfNewer,
+    /// Copy under a new name with numeric suffix, e.g. `photo (1).jpg`.
+    Rename,
+    /// Skip when content hashes match, otherwise copy under a new name.
+    SkipIfIdentical,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 5] = [
+        ConflictPolicy::Skip,
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::OverwriteIfNewer,
+        ConflictPolicy::Rename,
+        ConflictPolicy::SkipIfIdentical,
+    ];
+
+    /// Short name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::OverwriteIfNewer => "newer",
+            ConflictPolicy::Rename => "rename",
+            ConflictPolicy::SkipIfIdentical => "identical",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ConflictPolicy> {
+        ConflictPolicy::ALL
+            .iter()
+            .copied()
+            .find(|policy| policy.name() == name)
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            ConflictPolicy::Skip => "Skip existing files",
+            ConflictPolicy::Overwrite => "Overwrite existing files",
+            ConflictPolicy::OverwriteIfNewer => "Overwrite if source is newer",
+            ConflictPolicy::Rename => "Rename copied file",
+            ConflictPolicy::SkipIfIdentical => "Skip identical files, rename others",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Name of the checksum manifest written into output directory.
+pub const MANIFEST_FILE_NAME: &str = "collect-memories.sha256";
+
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    pub conflict_policy: ConflictPolicy,
+    /// Compare SHA-256 checksums of source and destination after copying.
+    pub verify: bool,
+    /// Write `sha256sum` compatible manifest of copied files into output directory.
+    pub write_manifest: bool,
+    /// With `TransferMode::Move` source files are removed after transferring
+    /// them and source directories left empty are pruned. Skipped files stay in place,
+    /// files reached through symbolic links are copied and stay in place too.
+    pub mode: TransferMode,
+    /// Keep modification and access times of files and mirrored directories.
+    pub preserve_times: bool,
+    /// Keep permissions of files and mirrored directories.
+    pub preserve_permissions: bool,
+    /// Keep extended attributes of files and mirrored directories, Unix only.
+    pub preserve_xattrs: bool,
+    /// Count of files copied at once, 0 uses one thread per CPU.
+    pub threads: usize,
+}
+
+impl Default for CopyOptions {
+    fn default() -> CopyOptions {
+        CopyOptions {
+            conflict_policy: ConflictPolicy::default(),
+            verify: false,
+            write_manifest: false,
+            mode: TransferMode::default(),
+            preserve_times: true,
+            preserve_permissions: false,
+            preserve_xattrs: false,
+            threads: 4,
+        }
+    }
+}
+
+/// File or directory `copy_files` failed to copy or create.
+#[derive(Debug)]
+pub struct CopyFailure {
+    /// Source file, or destination directory or file whose metadata could not be set.
+    pub path: PathBuf,
+    pub error: io::Error,
+    /// Failed file copy, `None` for directories.
+    pub file: Option<FileCopy>,
+}
+
+/// Counts of what `copy_files` did with planned files.
+#[derive(Debug, Default)]
+pub struct CopySummary {
+    /// Files copied to free destination.
+    pub copied: u32,
+    pub overwritten: u32,
+    pub renamed: u32,
+    pub skipped: u32,
+    /// Files skipped because destination had the same content.
+    pub identical: u32,
+    /// Files whose checksums matched after copying.
+    pub verified: u32,
+    /// Files whose checksums did not match, also listed in `failures`.
+    pub mismatched: u32,
+    /// Transferred files removed from the source directory.
+    pub moved: u32,
+    /// Source directories removed after moving files out of them.
+    pub pruned_directories: u32,
+    pub hard_linked: u32,
+    /// Files cloned by reflink.
+    pub cloned: u32,
+    /// Files copied because they could not be linked or cloned.
+    pub copied_instead: u32,
+    /// Files already transferred by an interrupted run, found in the journal.
+    pub resumed: u32,
+    /// Files reached through symbolic links copied instead of moved.
+    pub linked_kept: u32,
+    /// Unfinished temporary files of an interrupted run removed.
+    pub temporary_removed: u32,
+    /// Files left untouched because copying was cancelled.
+    pub cancelled: u32,
+    /// Failures in plan order.
+    pub failures: Vec<CopyFailure>,
+}
+
+impl CopySummary {
+    /// Adds counts of `other` and its failures after the ones already collected.
+    fn merge(&mut self, other: CopySummary) {
+        let CopySummary {
+            copied,
+            overwritten,
+            renamed,
+            skipped,
+            identical,
+            verified,
+            mismatched,
+            moved,
+            pruned_directories,
+            hard_linked,
+            cloned,
+            copied_instead,
+            resumed,
+            linked_kept,
+            temporary_removed,
+            cancelled,
+            failures,
+        } = other;
+        self.copied += copied;
+        self.overwritten += overwritten;
+        self.renamed += renamed;
+        self.skipped += skipped;
+        self.identical += identical;
+        self.verified += verified;
+        self.mismatched += mismatched;
+        self.moved += moved;
+        self.pruned_directories += pruned_directories;
+        self.hard_linked += hard_linked;
+        self.cloned += cloned;
+        self.copied_instead += copied_instead;
+        self.resumed += resumed;
+        self.linked_kept += linked_kept;
+        self.temporary_removed += temporary_removed;
+        self.cancelled += cancelled;
+        self.failures.extend(failures);
+    }
+
+    fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Copied => self.copied += 1,
+            Outcome::Overwritten => self.overwritten += 1,
+            Outcome::Renamed => self.renamed += 1,
+            Outcome::Skipped => self.skipped += 1,
+            Outcome::Identical => self.identical += 1,
+        }
+    }
+
+    /// Text report listing every failure with its error, one per line.
+    pub fn failure_report(&self) -> String {
+        self.failures
+            .iter()
+            .map(|failure| format!("{}\t{}\n", failure.path.to_string_lossy(), failure.error))
+            .collect()
+    }
+}
+
+impl fmt::Display for CopySummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} memories copied!",
+            self.copied + self.overwritten + self.renamed
+        )?;
+        for (cnt, description) in [
+            (self.overwritten, "existing files overwritten"),
+            (self.renamed, "copied under a new name"),
+            (self.skipped, "existing files skipped"),
+            (self.identical, "identical files skipped"),
+            (self.resumed, "already copied by an interrupted run"),
+            (self.verified, "verified by checksum"),
+            (self.moved, "removed from the source directory"),
+            (
+                self.linked_kept,
+                "reached through symbolic links copied and left in place",
+            ),
+            (self.hard_linked, "hard linked"),
+            (self.cloned, "cloned"),
+            (
+                self.copied_instead,
+                "copied because linking was not possible",
+            ),
+            (self.pruned_directories, "empty source directories removed"),
+            (
+                self.temporary_removed,
+                "unfinished files of an interrupted run removed",
+            ),
+            (self.cancelled, "not copied, copying was cancelled"),
+            (self.failures.len() as u32, "failed"),
+            (self.mismatched, "failed checksum verification"),
+        ]
+        .iter()
+        {
+            if *cnt > 0 {
+                write!(f, "\n{} {}", cnt, description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Progress of `copy_files` given to its callback when a file transfer starts
+/// and while file content is being copied.
+#[derive(Debug, Clone, Copy)]
+pub struct CopyProgress<'a> {
+    /// Destination of the file being transferred.
+    pub file: &'a Path,
+    /// Files processed before the current one.
+    pub files_done: usize,
+    pub files_total: usize,
+    /// Bytes of processed files and of the current file copied so far.
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Bytes actually copied so far, without skipped, resumed or renamed files.
+    /// Throughput is measured by them.
+    pub bytes_transferred: u64,
+    /// Bytes of the current file copied so far, zero when its transfer starts.
+    pub file_bytes_done: u64,
+}
+
+/// Result of copying a single planned file.
+struct Copied {
+    outcome: Outcome,
+    destination: PathBuf,
+    /// Destination checksum, when verified or needed for manifest.
+    hash: Option<FileHash>,
+    /// Content was compared with the source.
+    verified: bool,
+    /// `None` when the file was not transferred.
+    transferred: Option<Transferred>,
+}
+
+/// What happened with a single planned file.
+enum Outcome {
+    Copied,
+    Overwritten,
+    Renamed,
+    Skipped,
+    Identical,
+}
+
+/// Destination a file gets among files of the plan with the same destination.
+enum Claim {
+    /// File is the first planned to its destination.
+    Planned,
+    /// Skipped by `ConflictPolicy::Skip`, an earlier file takes the destination.
+    Skipped,
+    /// Written under a new name, an earlier file takes the destination.
+    Renamed(PathBuf),
+}
+
+/// Decides in plan order which of files planned to the same destination keeps it,
+/// so the result does not depend on the order files are copied in.
+/// Returns claims of plan files and destinations reserved by them.
+fn claim_destinations(
+    plan: &CopyPlan,
+    policy: ConflictPolicy,
+) -> (Vec<Claim>, Mutex<HashSet<PathBuf>>) {
+    let reserved = Mutex::new(
+        plan.files
+            .iter()
+            .map(|file| file.destination.clone())
+            .collect(),
+    );
+    let mut claimed = HashSet::new();
+    let claims = plan
+        .files
+        .iter()
+        .map(|file| {
+            if claimed.insert(&file.destination) {
+                Claim::Planned
+            } else if policy == ConflictPolicy::Skip {
+                Claim::Skipped
+            } else {
+                Claim::Renamed(renamed_destination(&file.destination, &reserved))
+            }
+        })
+        .collect();
+    (claims, reserved)
+}
+
+/// First free path of form `name (N).ext` next to `path`, not reserved by
+/// another file of this run. The path gets reserved.
+fn renamed_destination(path: &Path, reserved: &Mutex<HashSet<PathBuf>>) -> PathBuf {
+    let mut reserved = reserved.lock().unwrap();
+    let (stem, extension) = name_parts(path);
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists() && reserved.insert(candidate.clone()))
+        .expect("Free file name")
+}
+
+/// `destination` is `planned` or one of names `renamed_destination` gives it.
+fn is_planned_destination(destination: &Path, planned: &Path) -> bool {
+    if destination == planned {
+        return true;
+    }
+    if destination.parent() != planned.parent() {
+        return false;
+    }
+    let (stem, extension) = name_parts(planned);
+    let name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    name.strip_prefix(&format!("{} (", stem))
+        .and_then(|rest| rest.strip_suffix(&format!("){}", extension)))
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// File name without extension and the extension with its dot.
+fn name_parts(path: &Path) -> (String, String) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (stem.into_owned(), extension)
+}
+
+fn is_newer(source: &Path, destination: &Path) -> io::Result<bool> {
+    let source_modified = fs::metadata(source)?.modified()?;
+    let destination_modified = fs::metadata(destination)?.modified()?;
+    Ok(source_modified > destination_modified)
+}
+
+/// Copies `file` to the destination given by its `claim`,
+/// resolving an already existing destination file by `options.conflict_policy`.
+fn copy_file<F>(
+    file: &FileCopy,
+    claim: &Claim,
+    options: &CopyOptions,
+    journal: Option<&Mutex<Journal>>,
+    reserved: &Mutex<HashSet<PathBuf>>,
+    cancel: &CancelToken,
+    progress: &F,
+) -> io::Result<Copied>
+where
+    F: Fn(&Path, u64),
+{
+    let mut destination = file.destination.clone();
+    let mut outcome = Outcome::Copied;
+    match claim {
+        Claim::Planned => {}
+        Claim::Skipped => {
+            return Ok(Copied {
+                outcome: Outcome::Skipped,
+                destination,
+                hash: None,
+                verified: false,
+                transferred: None,
+            })
+        }
+        Claim::Renamed(renamed) => {
+            outcome = Outcome::Renamed;
+            destination = renamed.clone();
+        }
+    }
+    if matches!(claim, Claim::Planned) && destination.exists() {
+        // Writing over a hard link of the source would destroy the source.
+        if same_file(&file.source, &destination) {
+            return Ok(Copied {
+                outcome: Outcome::Identical,
+                destination,
+                hash: None,
+                verified: false,
+                transferred: None,
+            });
+        }
+        outcome = match options.conflict_policy {
+            ConflictPolicy::Skip => Outcome::Skipped,
+            ConflictPolicy::Overwrite => Outcome::Overwritten,
+            ConflictPolicy::OverwriteIfNewer => {
+                if is_newer(&file.source, &destination)? {
+                    Outcome::Overwritten
+                } else {
+                    Outcome::Skipped
+                }
+            }
+            ConflictPolicy::Rename => Outcome::Renamed,
+            ConflictPolicy::SkipIfIdentical => {
+                if hash_file(&file.source)? == hash_file(&destination)? {
+                    Outcome::Identical
+                } else {
+                    Outcome::Renamed
+                }
+            }
+        };
+        match outcome {
+            Outcome::Skipped | Outcome::Identical => {
+                return Ok(Copied {
+                    outcome,
+                    destination,
+                    hash: None,
+                    verified: false,
+                    transferred: None,
+                })
+            }
+            Outcome::Renamed => destination = renamed_destination(&destination, reserved),
+            _ => {}
+        }
+    }
+
+    progress(&destination, 0);
+    if let Some(journal) = journal {
+        journal.lock().unwrap().start(&file.source, &destination)?;
+    }
+    let transferred = transfer_file(
+        &file.source,
+        &destination,
+        options.mode,
+        cancel,
+        &|copied| progress(&destination, copied),
+    )?;
+
+    // Source is deleted after moving across devices, so the copy is always verified.
+    let verify = transferred != Transferred::Renamed
+        && (options.verify || options.mode == TransferMode::Move);
+    let mut hash = None;
+    if verify || options.write_manifest {
+        let destination_hash = hash_file(&destination)?;
+        if verify && hash_file(&file.source)? != destination_hash {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, ChecksumMismatch));
+        }
+        hash = Some(destination_hash);
+    }
+    Ok(Copied {
+        outcome,
+        destination,
+        hash,
+        verified: verify,
+        transferred: Some(transferred),
+    })
+}
+
+/// Destination of `file` when an interrupted run already transferred it.
+/// A destination whose transfer was interrupted is resumed only when it is
+/// the source itself, e.g. renamed or hard linked, any other file there is
+/// left to the conflict policy.
+///
+/// Only journaled destinations `file` is planned to, possibly renamed, are resumed.
+/// A finished destination is compared by checksum with the source before the
+/// source is removed by moving, or with `CopyOptions::verify`. When moving
+/// stopped after removing the source, a journaled destination of the planned
+/// size is all that is left of the file and is resumed as moved.
+fn resume_file(
+    file: &FileCopy,
+    journal: &Mutex<Journal>,
+    options: &CopyOptions,
+) -> io::Result<Option<PathBuf>> {
+    let planned = |destination: &PathBuf| is_planned_destination(destination, &file.destination);
+    let (completed, interrupted) = {
+        let journal = journal.lock().unwrap();
+        (
+            journal
+                .completed(&file.source)
+                .map(Path::to_owned)
+                .filter(planned),
+            journal
+                .interrupted(&file.source)
+                .map(Path::to_owned)
+                .filter(planned),
+        )
+    };
+    let source_removed = options.mode == TransferMode::Move
+        && fs::symlink_metadata(&file.source)
+            .is_err_and(|error| error.kind() == io::ErrorKind::NotFound);
+    if source_removed {
+        let finished = completed.is_some();
+        let destination = completed.or(interrupted).filter(|destination| {
+            fs::metadata(destination).is_ok_and(|metadata| metadata.len() == file.size)
+        });
+        if let (Some(destination), false) = (&destination, finished) {
+            journal.lock().unwrap().finish(&file.source, destination)?;
+        }
+        return Ok(destination);
+    }
+    if let Some(destination) = completed {
+        let mut complete =
+            fs::metadata(&destination).is_ok_and(|metadata| metadata.len() == file.size);
+        if complete && (options.verify || options.mode == TransferMode::Move) {
+            complete = same_file(&file.source, &destination)
+                || hash_file(&file.source)? == hash_file(&destination)?;
+        }
+        return Ok(Some(destination).filter(|_| complete));
+    }
+    let destination = match interrupted {
+        Some(destination) if destination.exists() => destination,
+        _ => return Ok(None),
+    };
+    if !same_file(&file.source, &destination) {
+        return Ok(None);
+    }
+    journal.lock().unwrap().finish(&file.source, &destination)?;
+    Ok(Some(destination))
+}
+
+/// Removes source directories left empty by moving files out of them,
+/// the scanned directory itself is kept. Returns count of removed directories.
+fn prune_source_directories(plan: &CopyPlan) -> u32 {
+    let mut directories = BTreeSet::new();
+    // Directories of linked files can be outside of the input directory.
+    for file in plan.files.iter().filter(|file| !file.linked) {
+        let mut directory = file.source.parent();
+        while let Some(path) = directory {
+            if path == plan.input_path || !path.starts_with(&plan.input_path) {
+                break;
+            }
+            directories.insert(path);
+            directory = path.parent();
+        }
+    }
+    // Children are sorted after their parents, so they are removed first.
+    directories
+        .iter()
+        .rev()
+        .filter(|directory| fs::remove_dir(directory).is_ok())
+        .count() as u32
+}
+
+/// Error payload for files whose copy differs from the source.
+#[derive(Debug)]
+struct ChecksumMismatch;
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Checksum of copied file does not match the source")
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+fn is_checksum_mismatch(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<ChecksumMismatch>())
+}
+
+/// Merges `entries` into `sha256sum` compatible manifest in `output_path`.
+fn write_manifest(output_path: &Path, entries: &[(PathBuf, FileHash)]) -> io::Result<PathBuf> {
+    let manifest_path = output_path.join(MANIFEST_FILE_NAME);
+
+    let mut manifest = BTreeMap::new();
+    match fs::read_to_string(&manifest_path) {
+        Ok(content) => {
+            for line in content.lines() {
+                if let Some((hash, path)) = line.split_once(' ') {
+                    let path = path.strip_prefix(|c| c == ' ' || c == '*').unwrap_or(path);
+                    manifest.insert(path.to_owned(), hash.to_owned());
+                }
+            }
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    for (destination, hash) in entries {
+        let relative_path = destination.strip_prefix(output_path).unwrap_or(destination);
+        manifest.insert(
+            relative_path.to_string_lossy().into_owned(),
+            hash_to_hex(hash),
+        );
+    }
+
+    let content: String = manifest
+        .iter()
+        .map(|(path, hash)| format!("{}  {}\n", hash, path))
+        .collect();
+    fs::write(&manifest_path, content)?;
+    Ok(manifest_path)
+}
+
+/// State shared by threads copying files of a single plan.
+struct CopyContext<'a, F> {
+    options: &'a CopyOptions,
+    cancel: &'a CancelToken,
+    journal: Option<Mutex<Journal>>,
+    journal_path: PathBuf,
+    /// Destinations used by files of this run.
+    reserved: Mutex<HashSet<PathBuf>>,
+    files_done: AtomicUsize,
+    files_total: usize,
+    /// Bytes of finished files and copied bytes of files being copied.
+    bytes_done: AtomicU64,
+    bytes_total: u64,
+    /// Copied bytes only, see `CopyProgress::bytes_transferred`.
+    bytes_transferred: AtomicU64,
+    callback: &'a F,
+}
+
+impl<'a, F> CopyContext<'a, F>
+where
+    F: Fn(&CopyProgress) + Sync,
+{
+    /// Copies single planned file, returning its summary and manifest entry.
+    fn copy_planned_file(
+        &self,
+        file: &FileCopy,
+        claim: &Claim,
+    ) -> (CopySummary, Option<(PathBuf, FileHash)>) {
+        let mut summary = CopySummary::default();
+        let mut manifest_entry = None;
+        if self.cancel.is_cancelled() {
+            summary.cancelled += 1;
+            return (summary, manifest_entry);
+        }
+
+        // Bytes of this file already added to `bytes_done`.
+        let reported = Cell::new(0);
+        let progress = |destination: &Path, file_bytes_done: u64| {
+            let added = file_bytes_done.saturating_sub(reported.replace(file_bytes_done));
+            let bytes_done = self.bytes_done.fetch_add(added, Ordering::Relaxed) + added;
+            let bytes_transferred =
+                self.bytes_transferred.fetch_add(added, Ordering::Relaxed) + added;
+            (self.callback)(&CopyProgress {
+                file: destination,
+                files_done: self.files_done.load(Ordering::Relaxed),
+                files_total: self.files_total,
+                bytes_done,
+                bytes_total: self.bytes_total,
+                bytes_transferred,
+                file_bytes_done,
+            })
+        };
+        self.transfer_planned_file(file, claim, &progress, &mut summary, &mut manifest_entry);
+
+        let rest = file.size.saturating_sub(reported.get());
+        self.bytes_done.fetch_add(rest, Ordering::Relaxed);
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+        (summary, manifest_entry)
+    }
+
+    fn transfer_planned_file<P>(
+        &self,
+        file: &FileCopy,
+        claim: &Claim,
+        progress: &P,
+        summary: &mut CopySummary,
+        manifest_entry: &mut Option<(PathBuf, FileHash)>,
+    ) where
+        P: Fn(&Path, u64),
+    {
+        // Moving would remove the file a link leads to, which can be outside
+        // of the input directory, so linked files are copied instead.
+        let kept = file.linked && self.options.mode == TransferMode::Move;
+        let copy_options;
+        let options = if kept {
+            copy_options = CopyOptions {
+                mode: TransferMode::Copy,
+                ..self.options.clone()
+            };
+            &copy_options
+        } else {
+            self.options
+        };
+        if let Some(journal) = &self.journal {
+            match resume_file(file, journal, options) {
+                Ok(None) => {}
+                Ok(Some(destination)) => {
+                    summary.resumed += 1;
+                    if kept {
+                        summary.linked_kept += 1;
+                    }
+                    if options.write_manifest {
+                        match hash_file(&destination) {
+                            Ok(hash) => *manifest_entry = Some((destination, hash)),
+                            Err(error) => summary.failures.push(CopyFailure {
+                                path: destination,
+                                error,
+                                file: None,
+                            }),
+                        }
+                    }
+                    if options.mode == TransferMode::Move {
+                        match fs::remove_file(&file.source) {
+                            // Removed by the interrupted run after transferring.
+                            Ok(()) => summary.moved += 1,
+                            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                                summary.moved += 1
+                            }
+                            Err(error) => summary.failures.push(CopyFailure {
+                                path: file.source.clone(),
+                                error,
+                                file: None,
+                            }),
+                        }
+                    }
+                    return;
+                }
+                Err(error) => {
+                    summary.failures.push(CopyFailure {
+                        path: file.source.clone(),
+                        error,
+                        file: Some(file.clone()),
+                    });
+                    return;
+                }
+            }
+        }
+        let source_metadata = fs::metadata(&file.source);
+        match copy_file(
+            file,
+            claim,
+            options,
+            self.journal.as_ref(),
+            &self.reserved,
+            self.cancel,
+            progress,
+        ) {
+            Ok(copied) => {
+                if copied.verified {
+                    summary.verified += 1;
+                }
+                let independent_copy = matches!(
+                    copied.transferred,
+                    Some(Transferred::Copied | Transferred::CopiedInstead | Transferred::Cloned)
+                );
+                if independent_copy {
+                    let preserved = source_metadata.and_then(|metadata| {
+                        preserve_read_metadata(
+                            &file.source,
+                            &metadata,
+                            &copied.destination,
+                            options,
+                        )
+                    });
+                    if let Err(error) = preserved {
+                        summary.failures.push(CopyFailure {
+                            path: copied.destination.clone(),
+                            error,
+                            file: None,
+                        });
+                    }
+                }
+                if let (Some(journal), Some(_)) = (&self.journal, copied.transferred) {
+                    let finished = journal
+                        .lock()
+                        .unwrap()
+                        .finish(&file.source, &copied.destination);
+                    if let Err(error) = finished {
+                        summary.failures.push(CopyFailure {
+                            path: self.journal_path.clone(),
+                            error,
+                            file: None,
+                        });
+                    }
+                }
+                if let Some(hash) = copied.hash {
+                    *manifest_entry = Some((copied.destination, hash));
+                }
+                if kept && copied.transferred.is_some() {
+                    summary.linked_kept += 1;
+                }
+                match copied.transferred {
+                    Some(Transferred::HardLinked) => summary.hard_linked += 1,
+                    Some(Transferred::Cloned) => summary.cloned += 1,
+                    Some(Transferred::CopiedInstead) => summary.copied_instead += 1,
+                    _ => {}
+                }
+                if let (TransferMode::Move, Some(transferred)) = (options.mode, copied.transferred)
+                {
+                    let removed = if transferred == Transferred::Renamed {
+                        Ok(())
+                    } else {
+                        fs::remove_file(&file.source)
+                    };
+                    match removed {
+                        Ok(()) => summary.moved += 1,
+                        Err(error) => summary.failures.push(CopyFailure {
+                            path: file.source.clone(),
+                            error,
+                            file: None,
+                        }),
+                    }
+                }
+                summary.record(copied.outcome);
+            }
+            Err(error) if is_cancelled_error(&error) => summary.cancelled += 1,
+            Err(error) => {
+                if is_checksum_mismatch(&error) {
+                    summary.mismatched += 1;
+                }
+                summary.failures.push(CopyFailure {
+                    path: file.source.clone(),
+                    error,
+                    file: Some(file.clone()),
+                })
+            }
+        }
+    }
+}
+
+/// Executes copy plan resolving already existing destination files
+/// by `options.conflict_policy`.
+/// Verification mismatches are reported as failures with `io::ErrorKind::InvalidData`.
+///
+/// Copying continues after errors, every failure is collected into the summary.
+/// With `TransferMode::Move` files are renamed when source and destination share
+/// a file system, otherwise copied, verified and removed from the source.
+///
+/// Copies are written to hidden temporary files renamed into place when complete,
+/// temporary files left by an interrupted run are removed.
+/// Transfers are recorded in a journal in the output directory, so a run
+/// interrupted midway can be resumed by executing the same plan again.
+/// Journal is removed after a run without failures.
+///
+/// `callback` receives progress when a file transfer starts and after every
+/// copied chunk of a file.
+///
+/// Several files are copied at once by `options.threads` threads, `callback`
+/// is called from all of them. Failures are listed in plan order.
+/// Of files planned to the same destination the first in plan order keeps it,
+/// the rest are skipped or renamed by `options.conflict_policy`.
+///
+/// When `cancel` is cancelled copying stops, files being copied are discarded
+/// and remaining files are counted in `CopySummary::cancelled`.
+pub fn copy_files<F>(
+    plan: &CopyPlan,
+    options: &CopyOptions,
+    cancel: &CancelToken,
+    callback: &F,
+) -> CopySummary
+where
+    F: Fn(&CopyProgress) + Sync,
+{
+    let mut summary = CopySummary::default();
+    for directory in &plan.directories {
+        if !directory.exists() {
+            if let Err(error) = fs::create_dir(directory) {
+                summary.failures.push(CopyFailure {
+                    path: directory.clone(),
+                    error,
+                    file: None,
+                });
+            }
+        }
+    }
+
+    let destination_directories: BTreeSet<&Path> = plan
+        .files
+        .iter()
+        .filter_map(|file| file.destination.parent())
+        .collect();
+    for directory in destination_directories {
+        match remove_temporary_files(directory) {
+            Ok(removed) => summary.temporary_removed += removed,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => summary.failures.push(CopyFailure {
+                path: directory.to_owned(),
+                error,
+                file: None,
+            }),
+        }
+    }
+
+    let journal = match Journal::open(&plan.input_path, &plan.output_path) {
+        Ok(journal) => Some(Mutex::new(journal)),
+        Err(error) => {
+            summary.failures.push(CopyFailure {
+                path: plan.output_path.join(JOURNAL_FILE_NAME),
+                error,
+                file: None,
+            });
+            None
+        }
+    };
+
+    // Moving files out of source directories changes their times,
+    // so they are read before transferring files.
+    let directory_metadata: Vec<_> = plan
+        .directories
+        .iter()
+        .filter_map(|directory| {
+            let source = plan.directory_sources.get(directory)?;
+            Some((directory, source, fs::metadata(source)))
+        })
+        .collect();
+
+    let (claims, reserved) = claim_destinations(plan, options.conflict_policy);
+    let context = CopyContext {
+        options,
+        cancel,
+        journal,
+        journal_path: plan.output_path.join(JOURNAL_FILE_NAME),
+        reserved,
+        files_done: AtomicUsize::new(0),
+        files_total: plan.files.len(),
+        bytes_done: AtomicU64::new(0),
+        bytes_total: plan.total_bytes,
+        bytes_transferred: AtomicU64::new(0),
+        callback,
+    };
+    let copy_all = || {
+        plan.files
+            .par_iter()
+            .zip(&claims)
+            .map(|(file, claim)| context.copy_planned_file(file, claim))
+            .collect::<Vec<_>>()
+    };
+    let results = match rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .build()
+    {
+        Ok(pool) => pool.install(copy_all),
+        Err(_) => copy_all(),
+    };
+
+    // Results are in plan order, independent of the order files were copied in.
+    let mut manifest_entries = Vec::new();
+    for (file_summary, manifest_entry) in results {
+        summary.merge(file_summary);
+        manifest_entries.extend(manifest_entry);
+    }
+
+    if options.write_manifest && !manifest_entries.is_empty() {
+        if let Err(error) = write_manifest(&plan.output_path, &manifest_entries) {
+            summary.failures.push(CopyFailure {
+                path: plan.output_path.join(MANIFEST_FILE_NAME),
+                error,
+                file: None,
+            });
+        }
+    }
+
+    // Removing the journal changes the output directory, so it goes first.
+    if let Some(journal) = context.journal {
+        let mut journal = journal.into_inner().unwrap();
+        let result = if summary.failures.is_empty() && summary.cancelled == 0 {
+            journal.remove()
+        } else {
+            journal.sync()
+        };
+        if let Err(error) = result {
+            summary.failures.push(CopyFailure {
+                path: plan.output_path.join(JOURNAL_FILE_NAME),
+                error,
+                file: None,
+            });
+        }
+    }
+
+    // Directory times change while files are written into them, so they are set last,
+    // before source directories are pruned.
+    for (directory, source, metadata) in directory_metadata.into_iter().rev() {
+        let result = metadata
+            .and_then(|metadata| preserve_read_metadata(source, &metadata, directory, options));
+        if let Err(error) = result {
+            summary.failures.push(CopyFailure {
+                path: directory.clone(),
+                error,
+                file: None,
+            });
+        }
+    }
+
+    if options.mode == TransferMode::Move {
+        summary.pruned_directories = prune_source_directories(plan);
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDirectory;
+    use crate::{retrieve_files_recursively, ScanOptions};
+
+    /// Plans copying of `.jpg` files from `in` to `out` of `dir`.
+    fn plan(dir: &TestDirectory) -> CopyPlan {
+        let input_path = dir.path().join("in");
+        let output_path = dir.path().join("out");
+        let scan = retrieve_files_recursively(
+            &input_path,
+            &ScanOptions::default(),
+            &CancelToken::new(),
+            &|path: &Path, _: &FileInfo| path.extension() == Some(OsStr::new("jpg")),
+            &|_: &Path| {},
+        )
+        .unwrap();
+        plan_copy(
+            &input_path,
+            &output_path,
+            &scan.files.unwrap(),
+            &PlanOptions::default(),
+            &CancelToken::new(),
+            &|_: &Path| {},
+        )
+    }
+
+    fn copy(plan: &CopyPlan, mode: TransferMode) -> CopySummary {
+        let options = CopyOptions {
+            mode,
+            ..Default::default()
+        };
+        copy_files(plan, &options, &CancelToken::new(), &|_: &CopyProgress| {})
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    fn colliding_plan(dir: &TestDirectory) -> CopyPlan {
+        let file = |source: &str| FileCopy {
+            source: dir.path().join(source),
+            destination: dir.path().join("out/a.jpg"),
+            size: 1,
+            conflict: false,
+            collision: true,
+            linked: false,
+        };
+        CopyPlan {
+            files: vec![file("in/a.jpg"), file("in/b/a.jpg"), file("in/c/a.jpg")],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_colliding_file_keeps_destination() {
+        let dir = TestDirectory::new("claim");
+        let plan = colliding_plan(&dir);
+
+        let (claims, _) = claim_destinations(&plan, ConflictPolicy::Skip);
+        assert!(matches!(
+            claims[..],
+            [Claim::Planned, Claim::Skipped, Claim::Skipped]
+        ));
+
+        dir.write("out/a (1).jpg", "old");
+        let (claims, reserved) = claim_destinations(&plan, ConflictPolicy::Rename);
+        let renamed: Vec<_> = claims
+            .iter()
+            .filter_map(|claim| match claim {
+                Claim::Renamed(destination) => Some(destination.clone()),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(claims[0], Claim::Planned));
+        assert_eq!(
+            renamed,
+            [
+                dir.path().join("out/a (2).jpg"),
+                dir.path().join("out/a (3).jpg")
+            ]
+        );
+        assert!(reserved.lock().unwrap().contains(&renamed[1]));
+    }
+
+    #[test]
+    fn renamed_destination_is_planned() {
+        let planned = Path::new("out/a.jpg");
+        assert!(is_planned_destination(planned, planned));
+        assert!(is_planned_destination(Path::new("out/a (1).jpg"), planned));
+        assert!(is_planned_destination(Path::new("out/a (12).jpg"), planned));
+        assert!(!is_planned_destination(Path::new("out/a ().jpg"), planned));
+        assert!(!is_planned_destination(Path::new("out/a (x).jpg"), planned));
+        assert!(!is_planned_destination(Path::new("out/a (1).png"), planned));
+        assert!(!is_planned_destination(Path::new("out/b (1).jpg"), planned));
+        assert!(!is_planned_destination(
+            Path::new("other/a (1).jpg"),
+            planned
+        ));
+        assert!(is_planned_destination(
+            Path::new("out/README (1)"),
+            Path::new("out/README")
+        ));
+    }
+
+    #[test]
+    fn counter_skips_existing_destinations() {
+        let dir = TestDirectory::new("counter");
+        dir.write("in/a.jpg", "a");
+        dir.write("in/b/a.jpg", "b");
+        dir.write("out/a-0001.jpg", "old");
+        let input_path = dir.path().join("in");
+        let scan = retrieve_files_recursively(
+            &input_path,
+            &ScanOptions::default(),
+            &CancelToken::new(),
+            &|_: &Path, _: &FileInfo| true,
+            &|_: &Path| {},
+        )
+        .unwrap();
+        let options = PlanOptions {
+            template: Some(PathTemplate::parse("{original_name}-{counter}.{ext}").unwrap()),
+            ..Default::default()
+        };
+
+        let plan = plan_copy(
+            &input_path,
+            &dir.path().join("out"),
+            &scan.files.unwrap(),
+            &options,
+            &CancelToken::new(),
+            &|_: &Path| {},
+        );
+
+        let mut destinations: Vec<_> = plan.files.iter().map(|file| &file.destination).collect();
+        destinations.sort();
+        assert_eq!(
+            destinations,
+            [
+                &dir.path().join("out/a-0002.jpg"),
+                &dir.path().join("out/a-0003.jpg")
+            ]
+        );
+        assert_eq!(plan.conflicts().count(), 0);
+    }
+
+    #[test]
+    fn move_removes_sources_and_prunes_emptied_directories() {
+        let dir = TestDirectory::new("move-prune");
+        dir.write("in/holiday/a.jpg", "a");
+        dir.write("in/keep/b.jpg", "b");
+        dir.write("in/keep/notes.txt", "notes");
+
+        let summary = copy(&plan(&dir), TransferMode::Move);
+
+        assert!(summary.failures.is_empty(), "{}", summary.failure_report());
+        assert_eq!(summary.moved, 2);
+        assert_eq!(summary.pruned_directories, 1);
+        assert_eq!(read(&dir.path().join("out/holiday/a.jpg")), "a");
+        assert_eq!(read(&dir.path().join("out/keep/b.jpg")), "b");
+        assert!(!dir.path().join("in/holiday").exists());
+        assert!(!dir.path().join("in/keep/b.jpg").exists());
+        assert!(dir.path().join("in/keep/notes.txt").exists());
+        assert!(dir.path().join("in").exists());
+        assert!(!dir.path().join("out").join(JOURNAL_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn resume_keeps_finished_files_and_leaves_interrupted_ones_to_conflict_policy() {
+        let dir = TestDirectory::new("resume");
+        let a = dir.write("in/a.jpg", "finished");
+        let b = dir.write("in/b.jpg", "interrupted");
+        let a_copy = dir.write("out/a.jpg", "finished");
+        let b_copy = dir.write("out/b.jpg", "inter");
+        {
+            let mut journal =
+                Journal::open(&dir.path().join("in"), &dir.path().join("out")).unwrap();
+            journal.start(&a, &a_copy).unwrap();
+            journal.finish(&a, &a_copy).unwrap();
+            journal.start(&b, &b_copy).unwrap();
+            journal.sync().unwrap();
+        }
+
+        let summary = copy(&plan(&dir), TransferMode::Copy);
+
+        assert!(summary.failures.is_empty(), "{}", summary.failure_report());
+        assert_eq!(summary.resumed, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(read(&a_copy), "finished");
+        assert_eq!(read(&b_copy), "inter");
+        assert!(!dir.path().join("out").join(JOURNAL_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn move_resumes_files_whose_source_was_removed() {
+        let dir = TestDirectory::new("resume-move");
+        let a = dir.write("in/a.jpg", "renamed");
+        let b = dir.write("in/b.jpg", "copied");
+        let plan = plan(&dir);
+        let a_copy = dir.path().join("out/a.jpg");
+        let b_copy = dir.write("out/b.jpg", "copied");
+        fs::rename(&a, &a_copy).unwrap();
+        fs::remove_file(&b).unwrap();
+        {
+            let mut journal =
+                Journal::open(&dir.path().join("in"), &dir.path().join("out")).unwrap();
+            journal.start(&a, &a_copy).unwrap();
+            journal.start(&b, &b_copy).unwrap();
+            journal.finish(&b, &b_copy).unwrap();
+            journal.sync().unwrap();
+        }
+
+        let summary = copy(&plan, TransferMode::Move);
+
+        assert!(summary.failures.is_empty(), "{}", summary.failure_report());
+        assert_eq!(summary.resumed, 2);
+        assert_eq!(summary.moved, 2);
+        assert_eq!(read(&a_copy), "renamed");
+        assert_eq!(read(&b_copy), "copied");
+    }
+
+    #[test]
+    fn move_ignores_journal_entry_for_another_destination() {
+        let dir = TestDirectory::new("stale-destination");
+        let a = dir.write("in/a.jpg", "good");
+        let old_copy = dir.write("out/old/a.jpg", "bad!");
+        {
+            let mut journal =
+                Journal::open(&dir.path().join("in"), &dir.path().join("out")).unwrap();
+            journal.finish(&a, &old_copy).unwrap();
+            journal.sync().unwrap();
+        }
+
+        let summary = copy(&plan(&dir), TransferMode::Move);
+
+        assert!(summary.failures.is_empty(), "{}", summary.failure_report());
+        assert_eq!(summary.resumed, 0);
+        assert_eq!(summary.moved, 1);
+        assert_eq!(read(&dir.path().join("out/a.jpg")), "good");
+    }
+
+    #[test]
+    fn move_keeps_source_when_finished_copy_differs() {
+        let dir = TestDirectory::new("stale-content");
+        let a = dir.write("in/a.jpg", "good");
+        let a_copy = dir.write("out/a.jpg", "bad!");
+        {
+            let mut journal =
+                Journal::open(&dir.path().join("in"), &dir.path().join("out")).unwrap();
+            journal.finish(&a, &a_copy).unwrap();
+            journal.sync().unwrap();
+        }
+
+        let summary = copy(&plan(&dir), TransferMode::Move);
+
+        assert_eq!(summary.resumed, 0);
+        assert_eq!(summary.moved, 0);
+        assert_eq!(read(&a), "good");
+        assert_eq!(read(&a_copy), "bad!");
+    }
+
+    #[test]
+    fn journal_of_another_input_directory_is_ignored() {
+        let dir = TestDirectory::new("other-input");
+        let a = dir.write("in/a.jpg", "good");
+        let a_copy = dir.write("out/a.jpg", "good");
+        fs::create_dir(dir.path().join("other")).unwrap();
+        {
+            let mut journal =
+                Journal::open(&dir.path().join("other"), &dir.path().join("out")).unwrap();
+            journal.finish(&a, &a_copy).unwrap();
+            journal.sync().unwrap();
+        }
+
+        let summary = copy(&plan(&dir), TransferMode::Copy);
+
+        assert_eq!(summary.resumed, 0);
+        assert_eq!(summary.skipped, 1);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::hash::{hash_file, FileHash};
+use crate::FileSystemItem;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Files with the same size and content.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub hash: FileHash,
+    /// Paths relative to the scanned directory, sorted.
+    pub files: Vec<PathBuf>,
+}
+
+fn collect_files(item: &FileSystemItem, current_path: &Path, files: &mut Vec<(PathBuf, u64)>) {
+    match item {
+        FileSystemItem::File(info) => files.push((current_path.to_owned(), info.size)),
+        FileSystemItem::Directory(directory) => {
+            for (name, child) in directory.content() {
+                collect_files(child, &current_path.join(name), files);
+            }
+        }
+    }
+}
+
+/// Finds files in `item` with identical content.
+///
+/// Files are first grouped by size, only files sharing their size with
+/// another file are hashed. Empty files and files that can not be read
+/// are never reported as duplicates. `callback` is called before hashing a file.
+pub fn find_duplicates<F>(
+    input_path: &Path,
+    item: &FileSystemItem,
+    callback: &F,
+) -> Vec<DuplicateGroup>
+where
+    F: Fn(&Path),
+{
+    let mut files = Vec::new();
+    collect_files(item, &PathBuf::new(), &mut files);
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for (file, size) in files {
+        if size > 0 {
+            by_size.entry(size).or_default().push(file);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (size, files) in by_size {
+        if files.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<FileHash, Vec<PathBuf>> = HashMap::new();
+        for file in files {
+            let source_path = input_path.join(&file);
+            callback(&source_path);
+            if let Ok(hash) = hash_file(&source_path) {
+                by_hash.entry(hash).or_default().push(file);
+            }
+        }
+        for (hash, mut files) in by_hash {
+            if files.len() > 1 {
+                files.sort();
+                groups.push(DuplicateGroup { size, hash, files });
+            }
+        }
+    }
+    groups.sort_by(|a, b| a.files.cmp(&b.files));
+    groups
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors of scanning directories and building file trees.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Directory listed the same name more than once.
+    DuplicateName(PathBuf),
+    /// Path is a file in one place and a directory in another,
+    /// e.g. on case insensitive file systems.
+    NameCollision(PathBuf),
+    /// Symbolic link leads back to a directory containing it.
+    SymlinkLoop(PathBuf),
+    /// Item could not be inserted into a tree view.
+    TreeInsertion(PathBuf),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::DuplicateName(path) => write!(
+                f,
+                "Multiple files with the same path '{}' in directory",
+                path.to_string_lossy()
+            ),
+            Error::NameCollision(path) => write!(
+                f,
+                "'{}' is both a file and a directory",
+                path.to_string_lossy()
+            ),
+            Error::SymlinkLoop(path) => write!(
+                f,
+                "'{}' links to a directory containing it, not followed",
+                path.to_string_lossy()
+            ),
+            Error::TreeInsertion(path) => write!(
+                f,
+                "'{}' could not be added to the file tree",
+                path.to_string_lossy()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
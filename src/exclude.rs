@@ -0,0 +1,72 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::io;
+use std::path::Path;
+
+/// Name of files with exclude patterns honored inside scanned directories.
+pub const IGNORE_FILE_NAME: &str = ".collectignore";
+
+/// Directories that seldom contain memories, in `.gitignore` syntax.
+pub static DEFAULT_EXCLUDE_PATTERNS: &[&str] = &[
+    "node_modules/",
+    "AppData/",
+    ".cache/",
+    "$RECYCLE.BIN/",
+    "/Windows/",
+    "System Volume Information/",
+    ".Trash-*/",
+    ".thumbnails/",
+    "__MACOSX/",
+];
+
+/// Exclude rules of a directory and all directories above it.
+pub(crate) struct ExcludeRules<'a> {
+    matcher: Gitignore,
+    parent: Option<&'a ExcludeRules<'a>>,
+}
+
+impl<'a> ExcludeRules<'a> {
+    /// Rules from `patterns` anchored at `root` directory.
+    pub fn new(root: &Path, patterns: &[String]) -> io::Result<ExcludeRules<'static>> {
+        let mut builder = GitignoreBuilder::new(root);
+        builder.case_insensitive(true).map_err(to_io_error)?;
+        for pattern in patterns {
+            builder.add_line(None, pattern).map_err(to_io_error)?;
+        }
+        Ok(ExcludeRules {
+            matcher: builder.build().map_err(to_io_error)?,
+            parent: None,
+        })
+    }
+
+    /// Extends rules with `IGNORE_FILE_NAME` file in `directory`, if there is one.
+    /// Invalid lines in the file are skipped.
+    pub fn with_ignore_file(&'a self, directory: &Path) -> Option<ExcludeRules<'a>> {
+        let ignore_file = directory.join(IGNORE_FILE_NAME);
+        if !ignore_file.is_file() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(directory);
+        builder.case_insensitive(true).ok()?;
+        builder.add(ignore_file);
+        Some(ExcludeRules {
+            matcher: builder.build().ok()?,
+            parent: Some(self),
+        })
+    }
+
+    /// Rules of deeper directories take precedence, like in `.gitignore`.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        match self.matcher.matched(path, is_dir) {
+            Match::Ignore(_) => true,
+            Match::Whitelist(_) => false,
+            Match::None => self
+                .parent
+                .is_some_and(|parent| parent.is_excluded(path, is_dir)),
+        }
+    }
+}
+
+fn to_io_error(err: ignore::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err.to_string())
+}
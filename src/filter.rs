@@ -0,0 +1,122 @@
+use crate::FileInfo;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Decides which scanned files are memories.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Lowercase file extensions to accept.
+    pub extensions: HashSet<String>,
+    /// Smallest accepted file size in bytes.
+    pub min_size: Option<u64>,
+    /// Largest accepted file size in bytes.
+    pub max_size: Option<u64>,
+    /// First accepted local modification date.
+    pub modified_from: Option<NaiveDate>,
+    /// Last accepted local modification date.
+    pub modified_to: Option<NaiveDate>,
+}
+
+impl FileFilter {
+    pub fn new(extensions: HashSet<String>) -> FileFilter {
+        FileFilter {
+            extensions,
+            ..Default::default()
+        }
+    }
+
+    /// Files with format detected from content pass when any extension
+    /// of the format is accepted. Files without known modification time
+    /// pass date checks.
+    pub fn matches(&self, path: &Path, info: &FileInfo) -> bool {
+        let extension_matches = match path.extension() {
+            Some(extension) => self
+                .extensions
+                .contains(&extension.to_string_lossy().to_lowercase()),
+            None => false,
+        };
+        let content_matches = info.detected
+            && info
+                .media_type
+                .extensions()
+                .iter()
+                .any(|extension| self.extensions.contains(*extension));
+        if !extension_matches && !content_matches {
+            return false;
+        }
+
+        if self.min_size.is_some_and(|min_size| info.size < min_size)
+            || self.max_size.is_some_and(|max_size| info.size > max_size)
+        {
+            return false;
+        }
+
+        if let Some(modified) = info.modified {
+            let modified = DateTime::<Local>::from(modified).date_naive();
+            if self.modified_from.is_some_and(|from| modified < from)
+                || self.modified_to.is_some_and(|to| modified > to)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Parses size like `20K`, `1.5M` or `2G` (binary units) into bytes.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim().to_uppercase();
+    let text = text
+        .strip_suffix("IB")
+        .or_else(|| text.strip_suffix('B'))
+        .unwrap_or(&text);
+    let (number, multiplier) = match text.chars().last()? {
+        'K' => (&text[..text.len() - 1], 1u64 << 10),
+        'M' => (&text[..text.len() - 1], 1 << 20),
+        'G' => (&text[..text.len() - 1], 1 << 30),
+        'T' => (&text[..text.len() - 1], 1 << 40),
+        _ => (text, 1),
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    if !number.is_finite() || number < 0.0 {
+        return None;
+    }
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Parses date in `YYYY-MM-DD` format.
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_reads_binary_units() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("20K"), Some(20 << 10));
+        assert_eq!(parse_size(" 1.5 mb "), Some(3 << 19));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_size("1t"), Some(1 << 40));
+    }
+
+    #[test]
+    fn parse_size_rejects_invalid_sizes() {
+        for text in [
+            "",
+            "M",
+            "-1K",
+            "ten",
+            "1X",
+            "nan",
+            "inf",
+            "-inf",
+            "infinityM",
+        ] {
+            assert_eq!(parse_size(text), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// SHA-256 digest of file content.
+pub type FileHash = [u8; 32];
+
+/// Hashes whole file content with SHA-256.
+pub fn hash_file(path: &Path) -> io::Result<FileHash> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Lowercase hexadecimal representation of the hash.
+pub fn hash_to_hex(hash: &FileHash) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
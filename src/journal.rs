@@ -0,0 +1,252 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Name of the journal kept in the output directory while copying.
+pub const JOURNAL_FILE_NAME: &str = "collect-memories.journal";
+
+/// Longest time finished transfers stay recorded only in memory.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Log of started and finished file transfers, so an interrupted
+/// `copy_files` run can be resumed.
+///
+/// First line is `roots` with the input and output directory, a journal of other
+/// directories is discarded. Every other line is `started` or `done`, source and
+/// destination path separated by tabs.
+///
+/// Lines are synced to the disk at most every `SYNC_INTERVAL` and by `sync`.
+/// A lost line is harmless: copies get their name only once complete, so a lost
+/// `started` leaves nothing incomplete behind, and a destination of a lost `done`
+/// is checked like one of an interrupted transfer or found as an existing file.
+pub(crate) struct Journal {
+    path: PathBuf,
+    file: File,
+    synced: Instant,
+    /// Destinations of started transfers not yet finished, by source.
+    started: HashMap<PathBuf, PathBuf>,
+    /// Destinations of finished transfers, by source.
+    done: HashMap<PathBuf, PathBuf>,
+}
+
+impl Journal {
+    /// Opens journal in `output_path`, reading entries of earlier runs
+    /// copying from `input_path`.
+    pub fn open(input_path: &Path, output_path: &Path) -> io::Result<Journal> {
+        let path = output_path.join(JOURNAL_FILE_NAME);
+        let roots = format!(
+            "roots\t{}\t{}",
+            escape(&canonical(input_path)),
+            escape(&canonical(output_path))
+        );
+        let mut started = HashMap::new();
+        let mut done = HashMap::new();
+        let mut resumed = false;
+        match fs::read_to_string(&path) {
+            Ok(content) if content.lines().next() == Some(roots.as_str()) => {
+                resumed = true;
+                for line in content.lines().skip(1) {
+                    let mut fields = line.split('\t');
+                    let (state, source, destination) =
+                        match (fields.next(), fields.next(), fields.next()) {
+                            (Some(state), Some(source), Some(destination)) => {
+                                (state, unescape(source), unescape(destination))
+                            }
+                            // Last line can be cut short by the interruption.
+                            _ => continue,
+                        };
+                    match state {
+                        "started" => {
+                            done.remove(&source);
+                            started.insert(source, destination);
+                        }
+                        "done" => {
+                            started.remove(&source);
+                            done.insert(source, destination);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        let file = if resumed {
+            OpenOptions::new().append(true).open(&path)?
+        } else {
+            let mut file = File::create(&path)?;
+            writeln!(file, "{}", roots)?;
+            file.sync_data()?;
+            file
+        };
+        Ok(Journal {
+            path,
+            file,
+            synced: Instant::now(),
+            started,
+            done,
+        })
+    }
+
+    /// Destination a finished transfer of `source` was written to.
+    pub fn completed(&self, source: &Path) -> Option<&Path> {
+        self.done.get(source).map(PathBuf::as_path)
+    }
+
+    /// Destination of a transfer of `source` that was interrupted,
+    /// the file there can be incomplete.
+    pub fn interrupted(&self, source: &Path) -> Option<&Path> {
+        self.started.get(source).map(PathBuf::as_path)
+    }
+
+    pub fn start(&mut self, source: &Path, destination: &Path) -> io::Result<()> {
+        self.write("started", source, destination)?;
+        self.done.remove(source);
+        self.started
+            .insert(source.to_owned(), destination.to_owned());
+        Ok(())
+    }
+
+    pub fn finish(&mut self, source: &Path, destination: &Path) -> io::Result<()> {
+        self.write("done", source, destination)?;
+        self.started.remove(source);
+        self.done.insert(source.to_owned(), destination.to_owned());
+        Ok(())
+    }
+
+    /// Writes recorded transfers to the disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.synced = Instant::now();
+        self.file.sync_data()
+    }
+
+    /// Removes the journal once nothing is left to resume.
+    pub fn remove(self) -> io::Result<()> {
+        drop(self.file);
+        fs::remove_file(&self.path)
+    }
+
+    fn write(&mut self, state: &str, source: &Path, destination: &Path) -> io::Result<()> {
+        writeln!(
+            self.file,
+            "{}\t{}\t{}",
+            state,
+            escape(source),
+            escape(destination)
+        )?;
+        if self.synced.elapsed() >= SYNC_INTERVAL {
+            self.sync()?;
+        }
+        Ok(())
+    }
+}
+
+/// Absolute form of an existing `path`, the path itself when it can not be resolved.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Escapes separators, so any path fits into a single field.
+/// Bytes of names that are not UTF-8 are written as `\xHH`.
+fn escape(path: &Path) -> String {
+    let mut field = String::new();
+    for chunk in path_bytes(path).utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => field.push_str("\\\\"),
+                '\t' => field.push_str("\\t"),
+                '\n' => field.push_str("\\n"),
+                c => field.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            field.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    field
+}
+
+fn unescape(field: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field.as_bytes();
+    while let Some((&byte, next)) = rest.split_first() {
+        rest = next;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        let hex = rest
+            .get(1..3)
+            .filter(|_| rest.first() == Some(&b'x'))
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        if let Some(byte) = hex {
+            bytes.push(byte);
+            rest = &rest[3..];
+            continue;
+        }
+        if let Some((&byte, next)) = rest.split_first() {
+            bytes.push(match byte {
+                b't' => b'\t',
+                b'n' => b'\n',
+                byte => byte,
+            });
+            rest = next;
+        }
+    }
+    path_from_bytes(bytes)
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+/// Names that are not valid Unicode are written lossily on this platform,
+/// their transfers are not resumed.
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_path_fits_one_field_and_unescapes_back() {
+        let path = Path::new("holiday\\2020/tab\there/new\nline\\t.jpg");
+        let field = escape(path);
+        assert!(!field.contains(['\t', '\n']));
+        assert_eq!(unescape(&field), path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_that_is_not_utf8_unescapes_back() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"photo\xff\\x41.jpg"));
+        let field = escape(path);
+        assert_eq!(field, "photo\\xff\\\\x41.jpg");
+        assert_eq!(unescape(&field), path);
+    }
+}
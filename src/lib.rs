@@ -1,92 +1,494 @@
+use chrono::{DateTime, Local};
+use exclude::ExcludeRules;
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::SystemTime;
+use symlink::{Ancestors, DirectoryId};
+
+mod cancel;
+mod copy;
+mod duplicates;
+mod error;
+mod exclude;
+mod filter;
+mod hash;
+mod journal;
+mod media;
+mod metadata;
+mod mounts;
+mod preserve;
+mod symlink;
+mod template;
+#[cfg(test)]
+mod testing;
+mod transfer;
+
+pub use cancel::CancelToken;
+pub use copy::{
+    copy_files, plan_copy, ConflictPolicy, CopyFailure, CopyOptions, CopyPlan, CopyProgress,
+    CopySummary, FileCopy, PlanOptions, MANIFEST_FILE_NAME,
+};
+pub use duplicates::{find_duplicates, DuplicateGroup};
+pub use error::Error;
+pub use exclude::{DEFAULT_EXCLUDE_PATTERNS, IGNORE_FILE_NAME};
+pub use filter::{parse_date, parse_size, FileFilter};
+pub use hash::{hash_file, hash_to_hex, FileHash};
+pub use journal::JOURNAL_FILE_NAME;
+pub use media::{detect_media_type, MediaType};
+pub use metadata::{camera_model, capture_date};
+pub use mounts::{mount_points, MountPoint};
+pub use symlink::SymlinkPolicy;
+pub use template::{PathTemplate, Placeholder, TemplateValues, DEFAULT_TEMPLATE, UNKNOWN_VALUE};
+pub use transfer::TransferMode;
 
 #[derive(Default)]
 pub struct Directory {
     content: HashMap<OsString, FileSystemItem>,
+    symlink: bool,
 }
 
 impl Directory {
     pub fn content(&self) -> &HashMap<OsString, FileSystemItem> {
         &self.content
     }
+
+    /// Directory was reached through a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.symlink
+    }
+
+    /// Removes item at `path` relative to this directory.
+    /// Directories left empty by the removal are removed too.
+    pub fn remove(&mut self, path: &Path) -> Option<FileSystemItem> {
+        let mut parts = path.iter();
+        let name = parts.next()?;
+        let rest = parts.as_path();
+        if rest.as_os_str().is_empty() {
+            return self.content.remove(name);
+        }
+
+        let (removed, now_empty) = match self.content.get_mut(name)? {
+            FileSystemItem::Directory(directory) => {
+                (directory.remove(rest)?, directory.content.is_empty())
+            }
+            FileSystemItem::File(_) => return None,
+        };
+        if now_empty {
+            self.content.remove(name);
+        }
+        Some(removed)
+    }
+}
+
+/// File metadata captured while scanning.
+#[derive(Debug, Clone, Default)]
+pub struct FileInfo {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// Creation time, not available on every platform and file system.
+    pub created: Option<SystemTime>,
+    pub media_type: MediaType,
+    /// `media_type` was recognized from file content instead of the extension.
+    pub detected: bool,
+    /// File is a symbolic link.
+    pub symlink: bool,
+    /// File or a directory containing it was reached through a symbolic link,
+    /// so the file can be located outside of the scanned directory.
+    pub linked: bool,
+}
+
+impl FileInfo {
+    pub fn new(path: &Path, metadata: &fs::Metadata) -> FileInfo {
+        FileInfo {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            created: metadata.created().ok(),
+            media_type: path
+                .extension()
+                .map(MediaType::from_extension)
+                .unwrap_or_default(),
+            detected: false,
+            symlink: false,
+            linked: false,
+        }
+    }
+
+    /// Usual extension of the detected format when `path` has an extension
+    /// not belonging to it, e.g. `jpg` for JPEG file `FILE0001.CHK`.
+    pub fn fixed_extension(&self, path: &Path) -> Option<&'static str> {
+        if !self.detected {
+            return None;
+        }
+        let extensions = self.media_type.extensions();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension {
+            Some(extension) if extensions.contains(&extension.as_str()) => None,
+            _ => extensions.first().copied(),
+        }
+    }
 }
 
 pub enum FileSystemItem {
-    File,
+    File(FileInfo),
     Directory(Directory),
 }
 
+impl FileSystemItem {
+    /// Count of files and their total size.
+    pub fn totals(&self) -> (u32, u64) {
+        match self {
+            FileSystemItem::File(info) => (1, info.size),
+            FileSystemItem::Directory(directory) => directory
+                .content
+                .values()
+                .map(FileSystemItem::totals)
+                .fold((0, 0), |(files, size), (child_files, child_size)| {
+                    (files + child_files, size + child_size)
+                }),
+        }
+    }
+}
+
+/// Scanning settings.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Files and directories to skip in `.gitignore` syntax,
+    /// anchored at the scanned directory and matched case insensitively.
+    pub exclude: Vec<String>,
+    /// Honor `IGNORE_FILE_NAME` files inside scanned directories.
+    pub use_ignore_files: bool,
+    /// Read start of every file to recognize its format by magic bytes.
+    pub detect_content: bool,
+    /// Count of threads reading directories, 0 uses one thread per CPU.
+    pub threads: usize,
+    /// Which symbolic links are followed.
+    pub symlinks: SymlinkPolicy,
+    /// Skip directories on other file systems, e.g. `/proc` or network drives.
+    /// Device numbers telling file systems apart are available on Unix only,
+    /// elsewhere the option has no effect.
+    pub one_file_system: bool,
+    /// Mount points scanned even with `one_file_system`,
+    /// as returned by `mount_points`.
+    pub include_mounts: Vec<PathBuf>,
+}
+
+/// Directory or file that could not be read while scanning.
+#[derive(Debug)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub error: Error,
+}
+
+/// Files found by `retrieve_files_recursively`.
+#[derive(Default)]
+pub struct ScanResult {
+    /// Tree of found files, `None` when nothing was found.
+    pub files: Option<FileSystemItem>,
+    /// Locations skipped because they could not be read, in path order.
+    pub errors: Vec<ScanError>,
+}
+
+impl ScanResult {
+    fn error(path: PathBuf, error: io::Error) -> ScanResult {
+        ScanResult {
+            files: None,
+            errors: vec![ScanError {
+                path,
+                error: error.into(),
+            }],
+        }
+    }
+
+    /// Text report listing every unreadable location with its error, one per line.
+    pub fn error_report(&self) -> String {
+        self.errors
+            .iter()
+            .map(|error| format!("{}\t{}\n", error.path.to_string_lossy(), error.error))
+            .collect()
+    }
+}
+
+/// Scans `path` for files accepted by `check`.
+/// `callback` is called for every file found before it is checked.
+/// Excluded directories are skipped without reading them.
+///
+/// Directories and files that can not be read are skipped and listed in
+/// `ScanResult::errors`, scanning continues with the rest.
+/// Error is returned only when scanning can not start, e.g. for an invalid
+/// exclude pattern.
+///
+/// Directories are scanned in parallel by `options.threads` threads, so `check`
+/// and `callback` are called from several threads in no particular order.
+/// The resulting tree and errors do not depend on the scheduling.
+///
+/// Symbolic links are followed as `options.symlinks` allows. A link to a directory
+/// being scanned is not followed again and is listed in `ScanResult::errors`.
+///
+/// With `options.one_file_system` directories on another file system than the
+/// directory containing them are skipped, unless listed in `options.include_mounts`.
+///
+/// When `cancel` is cancelled scanning stops, files found so far are returned.
 pub fn retrieve_files_recursively<F1, F2>(
-    path: &PathBuf,
+    path: &Path,
+    options: &ScanOptions,
+    cancel: &CancelToken,
     check: &F1,
     callback: &F2,
-) -> io::Result<Option<FileSystemItem>>
+) -> Result<ScanResult, Error>
 where
-    F1: Fn(&PathBuf) -> bool,
-    F2: Fn(&PathBuf),
+    F1: Fn(&Path, &FileInfo) -> bool + Sync,
+    F2: Fn(&Path) + Sync,
 {
-    let mut dir: Directory = Default::default();
-
-    let read_dir_iter = match path.read_dir() {
-        Ok(it) => it,
-        Err(err) => {
-            if err.kind() == io::ErrorKind::PermissionDenied {
-                return Ok(None);
-            } else {
-                return Err(err);
-            }
+    let rules = ExcludeRules::new(path, &options.exclude)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .build()
+        .map_err(io::Error::other)?;
+    let ancestors = if options.symlinks.follows_directories() {
+        match DirectoryId::of(path) {
+            Ok(id) => Some(Ancestors { id, parent: None }),
+            Err(err) => return Ok(ScanResult::error(path.to_owned(), err)),
         }
+    } else {
+        None
+    };
+    let device = if options.one_file_system {
+        match fs::metadata(path) {
+            Ok(metadata) => mounts::device(&metadata),
+            Err(err) => return Ok(ScanResult::error(path.to_owned(), err)),
+        }
+    } else {
+        None
     };
+    let context = ScanContext {
+        options,
+        cancel,
+        check,
+        callback,
+    };
+    Ok(pool.install(|| context.scan_directory(path, &rules, ancestors.as_ref(), device)))
+}
+
+/// State shared by threads scanning a single directory tree.
+struct ScanContext<'a, F1, F2> {
+    options: &'a ScanOptions,
+    cancel: &'a CancelToken,
+    check: &'a F1,
+    callback: &'a F2,
+}
+
+impl<'a, F1, F2> ScanContext<'a, F1, F2>
+where
+    F1: Fn(&Path, &FileInfo) -> bool + Sync,
+    F2: Fn(&Path) + Sync,
+{
+    /// `ancestors` are tracked only when links to directories are followed,
+    /// `device` of the directory only when staying on one file system.
+    fn scan_directory(
+        &self,
+        path: &Path,
+        rules: &ExcludeRules,
+        ancestors: Option<&Ancestors>,
+        device: Option<u64>,
+    ) -> ScanResult {
+        let mut dir: Directory = Default::default();
+        let mut errors = Vec::new();
+
+        let directory_rules = if self.options.use_ignore_files {
+            rules.with_ignore_file(path)
+        } else {
+            None
+        };
+        let rules = directory_rules.as_ref().unwrap_or(rules);
 
-    for child in read_dir_iter {
-        let child: fs::DirEntry = child?;
-        let file_type = child.file_type()?;
+        let read_dir_iter = match path.read_dir() {
+            Ok(it) => it,
+            Err(err) => return ScanResult::error(path.to_owned(), err),
+        };
 
-        let item: FileSystemItem = if file_type.is_dir() {
-            match retrieve_files_recursively(&child.path(), check, callback)? {
+        let mut children = Vec::new();
+        for child in read_dir_iter {
+            match child {
+                Ok(child) => children.push(child),
+                Err(error) => errors.push(ScanError {
+                    path: path.to_owned(),
+                    error: error.into(),
+                }),
+            }
+        }
+        // Children are scanned in parallel, sorting them keeps the order of errors
+        // the same on every run.
+        children.sort_by_key(fs::DirEntry::file_name);
+        let results: Vec<ScanResult> = children
+            .par_iter()
+            .map(|child| self.scan_entry(child, rules, ancestors, device))
+            .collect();
+
+        for (child, result) in children.iter().zip(results) {
+            errors.extend(result.errors);
+            let item = match result.files {
                 Some(item) => item,
                 None => continue,
+            };
+
+            use std::collections::hash_map::Entry;
+            match dir.content.entry(child.file_name()) {
+                Entry::Occupied(_occupied) => errors.push(ScanError {
+                    path: child.path(),
+                    error: Error::DuplicateName(child.path()),
+                }),
+                Entry::Vacant(vacant) => {
+                    vacant.insert(item);
+                }
             }
-        } else if file_type.is_file() {
-            let file_path = child.path();
-            callback(&file_path);
-            if check(&file_path) == false {
-                continue;
-            }
-            FileSystemItem::File
+        }
+
+        let files = if dir.content.is_empty() {
+            None
         } else {
-            continue;
+            Some(FileSystemItem::Directory(dir))
         };
+        ScanResult { files, errors }
+    }
 
-        use std::collections::hash_map::Entry;
-        match dir.content.entry(
-            child
-                .path()
-                .file_name()
-                .expect("Non empty filename!")
-                .to_owned(),
-        ) {
-            Entry::Occupied(_occupied) => {
-                panic!(
-                    "Multiple files with the same path '{}' in directory!",
-                    child.path().to_string_lossy()
-                );
+    /// Scans single directory entry, without files when it is skipped
+    /// or contains no memories.
+    fn scan_entry(
+        &self,
+        child: &fs::DirEntry,
+        rules: &ExcludeRules,
+        ancestors: Option<&Ancestors>,
+        device: Option<u64>,
+    ) -> ScanResult {
+        let options = self.options;
+        if self.cancel.is_cancelled() {
+            return ScanResult::default();
+        }
+        let mut file_type = match child.file_type() {
+            Ok(file_type) => file_type,
+            Err(err) => return ScanResult::error(child.path(), err),
+        };
+        let symlink = file_type.is_symlink();
+        // Metadata of the file or directory the link points to.
+        let mut followed = None;
+        if symlink {
+            if !options.symlinks.follows_files() {
+                return ScanResult::default();
             }
-            Entry::Vacant(vacant) => {
-                vacant.insert(item);
+            let metadata = match fs::metadata(child.path()) {
+                Ok(metadata) => metadata,
+                Err(err) => return ScanResult::error(child.path(), err),
+            };
+            file_type = metadata.file_type();
+            if file_type.is_dir() && !options.symlinks.follows_directories() {
+                return ScanResult::default();
             }
+            followed = Some(metadata);
+        }
+        if rules.is_excluded(&child.path(), file_type.is_dir()) {
+            return ScanResult::default();
+        }
+        let metadata = || match followed {
+            Some(metadata) => Ok(metadata),
+            None => child.metadata(),
+        };
+
+        if file_type.is_dir() {
+            let path = child.path();
+            let device = match device {
+                Some(parent_device) => {
+                    let child_device = match metadata() {
+                        Ok(metadata) => mounts::device(&metadata),
+                        Err(err) => return ScanResult::error(path, err),
+                    };
+                    if child_device != Some(parent_device)
+                        && !options.include_mounts.contains(&path)
+                    {
+                        return ScanResult::default();
+                    }
+                    child_device
+                }
+                None => None,
+            };
+            let directory;
+            let ancestors = match ancestors {
+                Some(parent) => {
+                    let id = match DirectoryId::of(&path) {
+                        Ok(id) => id,
+                        Err(err) => return ScanResult::error(path, err),
+                    };
+                    if parent.contains(&id) {
+                        return ScanResult {
+                            files: None,
+                            errors: vec![ScanError {
+                                path: path.clone(),
+                                error: Error::SymlinkLoop(path),
+                            }],
+                        };
+                    }
+                    directory = Ancestors {
+                        id,
+                        parent: Some(parent),
+                    };
+                    Some(&directory)
+                }
+                None => None,
+            };
+            let mut result = self.scan_directory(&path, rules, ancestors, device);
+            if let (true, Some(item)) = (symlink, &mut result.files) {
+                if let FileSystemItem::Directory(dir) = item {
+                    dir.symlink = true;
+                }
+                mark_linked(item);
+            }
+            result
+        } else if file_type.is_file() {
+            let file_path = child.path();
+            (self.callback)(&file_path);
+            let metadata = match metadata() {
+                Ok(metadata) => metadata,
+                Err(err) => return ScanResult::error(file_path, err),
+            };
+            let mut info = FileInfo::new(&file_path, &metadata);
+            info.symlink = symlink;
+            info.linked = symlink;
+            if options.detect_content {
+                if let Ok(media_type) = detect_media_type(&file_path) {
+                    if media_type != MediaType::Unknown {
+                        info.media_type = media_type;
+                        info.detected = true;
+                    }
+                }
+            }
+            if !(self.check)(&file_path, &info) {
+                return ScanResult::default();
+            }
+            ScanResult {
+                files: Some(FileSystemItem::File(info)),
+                errors: Vec::new(),
+            }
+        } else {
+            ScanResult::default()
         }
     }
+}
 
-    if dir.content.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(FileSystemItem::Directory(dir)))
+/// Marks files in `item` reached through a symbolic link to a directory.
+fn mark_linked(item: &mut FileSystemItem) {
+    match item {
+        FileSystemItem::File(info) => info.linked = true,
+        FileSystemItem::Directory(directory) => {
+            for child in directory.content.values_mut() {
+                mark_linked(child);
+            }
+        }
     }
 }
 
@@ -145,81 +547,70 @@ impl ReversePath {
 fn build_directory_tree<'a>(
     root_dir: &'a mut Directory,
     part: &ReversePathPart,
-) -> &'a mut Directory {
+) -> Result<&'a mut Directory, Error> {
     let last_part = match part.prefix.as_ref() {
-        Some(part) => build_directory_tree(root_dir, part.as_ref()),
+        Some(part) => build_directory_tree(root_dir, part.as_ref())?,
         None => root_dir,
     };
 
-    let directory =
-        match last_part
-            .content
-            .entry(part.part.to_owned())
-            .or_insert(FileSystemItem::Directory(Directory {
-                ..Default::default()
-            })) {
-            FileSystemItem::Directory(x) => x,
-            _ => panic!("Unexpected file and directory name collision!"),
-        };
-
-    directory
+    match last_part
+        .content
+        .entry(part.part.to_owned())
+        .or_insert(FileSystemItem::Directory(Directory {
+            ..Default::default()
+        })) {
+        FileSystemItem::Directory(x) => Ok(x),
+        FileSystemItem::File(_) => Err(Error::NameCollision(part.path())),
+    }
 }
 
-fn build_file_tree(root_dir: &mut Directory, part: &ReversePathPart) {
+fn build_file_tree(
+    root_dir: &mut Directory,
+    part: &ReversePathPart,
+    info: FileInfo,
+) -> Result<(), Error> {
     let last_part = match part.prefix.as_ref() {
-        Some(part) => build_directory_tree(root_dir, part.as_ref()),
+        Some(part) => build_directory_tree(root_dir, part.as_ref())?,
         None => root_dir,
     };
-    last_part
-        .content
-        .insert(part.part.to_owned(), FileSystemItem::File);
+    match last_part.content.get(&part.part) {
+        Some(FileSystemItem::Directory(_)) => Err(Error::NameCollision(part.path())),
+        _ => {
+            last_part
+                .content
+                .insert(part.part.to_owned(), FileSystemItem::File(info));
+            Ok(())
+        }
+    }
 }
 
-pub fn reverse_file_paths(paths: &Vec<ReversePath>) -> Directory {
+/// Builds directory tree from file paths, fails when a path is used
+/// both for a file and a directory.
+pub fn reverse_file_paths(files: &[(ReversePath, FileInfo)]) -> Result<Directory, Error> {
     let mut dir = Default::default();
-    for path in paths {
-        build_file_tree(&mut dir, path.last_part.as_ref());
+    for (path, info) in files {
+        build_file_tree(&mut dir, path.last_part.as_ref(), info.clone())?;
     }
-    dir
+    Ok(dir)
 }
 
-pub fn copy_files<F>(
-    input_path: &PathBuf,
-    output_path: &PathBuf,
-    item: &FileSystemItem,
-    current_path: &PathBuf,
-    callback: &F,
-) -> io::Result<u32>
-where
-    F: Fn(&PathBuf),
-{
-    let destination_path = output_path.join(current_path);
-
-    match item {
-        FileSystemItem::File => {
-            if destination_path.exists() {
-                return Err(io::Error::new(
-                    io::ErrorKind::AlreadyExists,
-                    format!(
-                        "Destination file {} already exists!",
-                        destination_path.to_string_lossy()
-                    ),
-                ));
-            }
-            callback(&destination_path);
-            fs::copy(input_path.join(current_path), destination_path)?;
-            Ok(1)
-        }
-        FileSystemItem::Directory(directory) => {
-            if !destination_path.exists() {
-                fs::create_dir(destination_path)?;
-            }
-            let mut file_cnt = 0;
-            for (child_path, child_item) in directory.content.iter() {
-                let child_path = current_path.join(child_path);
-                file_cnt += copy_files(input_path, output_path, child_item, &child_path, callback)?;
-            }
-            Ok(file_cnt)
-        }
+/// Formats byte count in human readable binary units.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
     }
 }
+
+/// Formats time as local calendar date `YYYY-MM-DD`.
+pub fn format_date(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%Y-%m-%d").to_string()
+}
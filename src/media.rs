@@ -0,0 +1,234 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Count of bytes at the start of a file `detect_media_type` looks at.
+const HEADER_LENGTH: usize = 32;
+
+/// Format of a media file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MediaType {
+    Jpeg,
+    Png,
+    Gif,
+    Bmp,
+    Tiff,
+    Webp,
+    Heic,
+    Mp4,
+    Mov,
+    ThreeGp,
+    Avi,
+    Mpeg,
+    Wmv,
+    Mkv,
+    #[default]
+    Unknown,
+}
+
+impl MediaType {
+    pub const ALL: [MediaType; 14] = [
+        MediaType::Jpeg,
+        MediaType::Png,
+        MediaType::Gif,
+        MediaType::Bmp,
+        MediaType::Tiff,
+        MediaType::Webp,
+        MediaType::Heic,
+        MediaType::Mp4,
+        MediaType::Mov,
+        MediaType::ThreeGp,
+        MediaType::Avi,
+        MediaType::Mpeg,
+        MediaType::Wmv,
+        MediaType::Mkv,
+    ];
+
+    /// Lowercase file extensions of the format, the usual one first.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            MediaType::Jpeg => &["jpg", "jpeg", "jpe"],
+            MediaType::Png => &["png"],
+            MediaType::Gif => &["gif"],
+            MediaType::Bmp => &["bmp"],
+            MediaType::Tiff => &["tif", "tiff"],
+            MediaType::Webp => &["webp"],
+            MediaType::Heic => &["heic", "heif"],
+            MediaType::Mp4 => &["mp4", "m4v"],
+            MediaType::Mov => &["mov", "qt"],
+            MediaType::ThreeGp => &["3gp", "3g2"],
+            MediaType::Avi => &["avi"],
+            MediaType::Mpeg => &["mpg", "mpeg", "mpe", "vob"],
+            MediaType::Wmv => &["wmv", "asf"],
+            MediaType::Mkv => &["mkv", "webm"],
+            MediaType::Unknown => &[],
+        }
+    }
+
+    /// Guesses format from case insensitive file extension.
+    pub fn from_extension(extension: &OsStr) -> MediaType {
+        let extension = extension.to_string_lossy().to_lowercase();
+        MediaType::ALL
+            .iter()
+            .copied()
+            .find(|media_type| media_type.extensions().contains(&extension.as_str()))
+            .unwrap_or(MediaType::Unknown)
+    }
+
+    /// Recognizes format from magic bytes at the start of a file.
+    pub fn from_header(header: &[u8]) -> MediaType {
+        let at =
+            |offset: usize, bytes: &[u8]| header.get(offset..offset + bytes.len()) == Some(bytes);
+
+        if at(0, &[0xFF, 0xD8, 0xFF]) {
+            MediaType::Jpeg
+        } else if at(0, b"\x89PNG\r\n\x1A\n") {
+            MediaType::Png
+        } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+            MediaType::Gif
+        } else if at(0, b"BM") && bmp_info_header(header) {
+            MediaType::Bmp
+        } else if at(0, b"II*\0") || at(0, b"MM\0*") {
+            MediaType::Tiff
+        } else if at(0, b"RIFF") && at(8, b"WEBP") {
+            MediaType::Webp
+        } else if at(0, b"RIFF") && at(8, b"AVI ") {
+            MediaType::Avi
+        } else if at(4, b"ftyp") {
+            header
+                .get(8..12)
+                .map_or(MediaType::Unknown, MediaType::from_brand)
+        } else if at(4, b"moov") || at(4, b"mdat") || at(4, b"wide") {
+            MediaType::Mov
+        } else if at(0, &[0x00, 0x00, 0x01, 0xBA]) || at(0, &[0x00, 0x00, 0x01, 0xB3]) {
+            MediaType::Mpeg
+        } else if at(0, &[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11]) {
+            MediaType::Wmv
+        } else if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+            MediaType::Mkv
+        } else {
+            MediaType::Unknown
+        }
+    }
+
+    /// Format of ISO base media file (MP4, QuickTime, HEIF) by its major brand.
+    fn from_brand(brand: &[u8]) -> MediaType {
+        match brand {
+            b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1" => {
+                MediaType::Heic
+            }
+            b"avif" | b"avis" => MediaType::Unknown,
+            b"qt  " => MediaType::Mov,
+            _ if brand.starts_with(b"3g") => MediaType::ThreeGp,
+            _ => MediaType::Mp4,
+        }
+    }
+
+    pub fn is_video(self) -> bool {
+        matches!(
+            self,
+            MediaType::Mp4
+                | MediaType::Mov
+                | MediaType::ThreeGp
+                | MediaType::Avi
+                | MediaType::Mpeg
+                | MediaType::Wmv
+                | MediaType::Mkv
+        )
+    }
+
+    pub fn is_image(self) -> bool {
+        self != MediaType::Unknown && !self.is_video()
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MediaType::Jpeg => "JPEG",
+            MediaType::Png => "PNG",
+            MediaType::Gif => "GIF",
+            MediaType::Bmp => "BMP",
+            MediaType::Tiff => "TIFF",
+            MediaType::Webp => "WebP",
+            MediaType::Heic => "HEIC",
+            MediaType::Mp4 => "MP4",
+            MediaType::Mov => "QuickTime",
+            MediaType::ThreeGp => "3GP",
+            MediaType::Avi => "AVI",
+            MediaType::Mpeg => "MPEG",
+            MediaType::Wmv => "WMV",
+            MediaType::Mkv => "Matroska",
+            MediaType::Unknown => "Unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// BMP files start with `BM` too often by chance, so size of
+/// the info header following the file header is checked as well.
+fn bmp_info_header(header: &[u8]) -> bool {
+    match header.get(14..18) {
+        Some(&[size, 0, 0, 0]) => matches!(size, 12 | 40 | 52 | 56 | 64 | 108 | 124),
+        _ => false,
+    }
+}
+
+/// Reads the start of the file at `path` and recognizes its format
+/// regardless of the file extension.
+pub fn detect_media_type(path: &Path) -> io::Result<MediaType> {
+    let mut header = Vec::with_capacity(HEADER_LENGTH);
+    File::open(path)?
+        .take(HEADER_LENGTH as u64)
+        .read_to_end(&mut header)?;
+    Ok(MediaType::from_header(&header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `start` followed by zeros, as long as headers read from files.
+    fn header(start: &[u8]) -> Vec<u8> {
+        let mut header = start.to_vec();
+        header.resize(HEADER_LENGTH.max(start.len()), 0);
+        header
+    }
+
+    #[test]
+    fn from_header_recognizes_formats() {
+        let headers: [(&[u8], MediaType); 15] = [
+            (b"\xFF\xD8\xFF\xE1", MediaType::Jpeg),
+            (b"\x89PNG\r\n\x1A\n", MediaType::Png),
+            (b"GIF89a", MediaType::Gif),
+            (b"BM\x36\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0", MediaType::Bmp),
+            (b"II*\0", MediaType::Tiff),
+            (b"RIFF\0\0\0\0WEBPVP8 ", MediaType::Webp),
+            (b"RIFF\0\0\0\0AVI LIST", MediaType::Avi),
+            (b"\0\0\0\x18ftypheic", MediaType::Heic),
+            (b"\0\0\0\x18ftypisom", MediaType::Mp4),
+            (b"\0\0\0\x14ftypqt  ", MediaType::Mov),
+            (b"\0\0\0\x08wide", MediaType::Mov),
+            (b"\0\0\0\x18ftyp3gp4", MediaType::ThreeGp),
+            (b"\0\0\x01\xBA", MediaType::Mpeg),
+            (b"\x30\x26\xB2\x75\x8E\x66\xCF\x11", MediaType::Wmv),
+            (b"\x1A\x45\xDF\xA3", MediaType::Mkv),
+        ];
+        for (start, media_type) in headers {
+            assert_eq!(MediaType::from_header(&header(start)), media_type);
+        }
+    }
+
+    #[test]
+    fn from_header_rejects_text_starting_like_bitmap() {
+        let text = header(b"BMW service history, 2019 to 2023\n");
+        assert_eq!(MediaType::from_header(&text), MediaType::Unknown);
+        assert_eq!(MediaType::from_header(b""), MediaType::Unknown);
+        assert_eq!(
+            MediaType::from_header(&header(b"\0\0\0\x18ftypavif")),
+            MediaType::Unknown
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{FileInfo, MediaType};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use exif::{Exif, In, Reader, Tag, Value};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Seconds between 1904-01-01 (MP4 and QuickTime epoch) and 1970-01-01.
+const CONTAINER_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+/// Local date and time the memory was captured.
+///
+/// Taken from EXIF `DateTimeOriginal` of images, creation time of MP4,
+/// QuickTime and 3GP videos, or file modification time when the file
+/// has no such metadata.
+pub fn capture_date(path: &Path, info: &FileInfo) -> Option<NaiveDateTime> {
+    let date = match info.media_type {
+        MediaType::Mp4 | MediaType::Mov | MediaType::ThreeGp => container_date(path),
+        media_type if media_type.is_image() => exif_date(path),
+        _ => None,
+    };
+    date.or_else(|| {
+        info.modified
+            .map(|modified| DateTime::<Local>::from(modified).naive_local())
+    })
+}
+
+/// Camera model from EXIF `Model` tag of images.
+pub fn camera_model(path: &Path, info: &FileInfo) -> Option<String> {
+    if !info.media_type.is_image() {
+        return None;
+    }
+    let exif = read_exif(path)?;
+    let field = exif.get_field(Tag::Model, In::PRIMARY)?;
+    match &field.value {
+        Value::Ascii(values) => {
+            let model = String::from_utf8_lossy(values.first()?);
+            let model = model.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+            Some(model.to_owned()).filter(|model| !model.is_empty())
+        }
+        _ => None,
+    }
+}
+
+fn read_exif(path: &Path) -> Option<Exif> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    Reader::new().read_from_container(&mut file).ok()
+}
+
+fn exif_date(path: &Path) -> Option<NaiveDateTime> {
+    let exif = read_exif(path)?;
+    [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+        .iter()
+        .find_map(|tag| match &exif.get_field(*tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => {
+                let date = exif::DateTime::from_ascii(values.first()?).ok()?;
+                NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())?
+                    .and_hms_opt(date.hour.into(), date.minute.into(), date.second.into())
+            }
+            _ => None,
+        })
+}
+
+/// Reads creation time from the movie header (`moov/mvhd` box)
+/// of an ISO base media file. The time is stored in UTC.
+fn container_date(path: &Path) -> Option<NaiveDateTime> {
+    let mut file = File::open(path).ok()?;
+    let file_size = file.metadata().ok()?.len();
+    let (moov_start, moov_end) = find_box(&mut file, 0, file_size, b"moov")?;
+    let (mvhd_start, _) = find_box(&mut file, moov_start, moov_end, b"mvhd")?;
+
+    file.seek(SeekFrom::Start(mvhd_start)).ok()?;
+    let mut version_and_flags = [0u8; 4];
+    file.read_exact(&mut version_and_flags).ok()?;
+    let seconds = if version_and_flags[0] == 1 {
+        let mut time = [0u8; 8];
+        file.read_exact(&mut time).ok()?;
+        u64::from_be_bytes(time)
+    } else {
+        let mut time = [0u8; 4];
+        file.read_exact(&mut time).ok()?;
+        u32::from_be_bytes(time).into()
+    };
+    // Zero is written by devices without a clock.
+    if seconds <= CONTAINER_EPOCH_OFFSET {
+        return None;
+    }
+    let time = Utc
+        .timestamp_opt((seconds - CONTAINER_EPOCH_OFFSET) as i64, 0)
+        .single()?;
+    Some(time.with_timezone(&Local).naive_local())
+}
+
+/// Searches boxes between `start` and `end` offsets for the first box
+/// of `box_type`, returns offsets of its content.
+fn find_box(file: &mut File, start: u64, end: u64, box_type: &[u8; 4]) -> Option<(u64, u64)> {
+    let mut position = start;
+    while position + 8 <= end {
+        file.seek(SeekFrom::Start(position)).ok()?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).ok()?;
+        let mut header_size = 8;
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => end - position,
+            1 => {
+                let mut large_size = [0u8; 8];
+                file.read_exact(&mut large_size).ok()?;
+                header_size = 16;
+                u64::from_be_bytes(large_size)
+            }
+            size => size.into(),
+        };
+        if size < header_size {
+            return None;
+        }
+        // Sizes of corrupt boxes can point past the end or overflow.
+        let box_end = position
+            .checked_add(size)
+            .filter(|box_end| *box_end <= end)?;
+        if &header[4..8] == box_type {
+            return Some((position + header_size, box_end));
+        }
+        position = box_end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDirectory;
+    use std::fs;
+
+    #[test]
+    fn find_box_rejects_overflowing_size() {
+        let dir = TestDirectory::new("find-box");
+        let path = dir.path().join("a.mp4");
+        let mut content = vec![0, 0, 0, 1];
+        content.extend_from_slice(b"ftyp");
+        content.extend_from_slice(&u64::MAX.to_be_bytes());
+        fs::write(&path, &content).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(find_box(&mut file, 0, content.len() as u64, b"moov"), None);
+        assert_eq!(find_box(&mut file, 0, content.len() as u64, b"ftyp"), None);
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File system mounted inside a scanned directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountPoint {
+    /// Mount point, under the scanned directory path as given.
+    pub path: PathBuf,
+    /// File system type, e.g. `ext4`, `nfs` or `proc`.
+    pub file_system: String,
+    /// Mounted device or share, e.g. `/dev/sdb1` or `server:/photos`.
+    pub source: String,
+}
+
+/// Lists file systems mounted inside `path`, ordered by path.
+///
+/// Mounts are read from `/proc/self/mountinfo`,
+/// on other platforms than Linux the list is empty.
+pub fn mount_points(path: &Path) -> io::Result<Vec<MountPoint>> {
+    let canonical = fs::canonicalize(path)?;
+    let mut mounts: Vec<MountPoint> = Vec::new();
+    for (mount_point, file_system, source) in system_mounts()? {
+        let relative = match mount_point.strip_prefix(&canonical) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => continue,
+        };
+        let mount = MountPoint {
+            path: path.join(relative),
+            file_system,
+            source,
+        };
+        // Later mount on the same path hides the earlier one.
+        match mounts.iter_mut().find(|other| other.path == mount.path) {
+            Some(other) => *other = mount,
+            None => mounts.push(mount),
+        }
+    }
+    mounts.sort_by(|first, second| first.path.cmp(&second.path));
+    Ok(mounts)
+}
+
+/// Mount point, file system type and source of every mounted file system.
+#[cfg(target_os = "linux")]
+fn system_mounts() -> io::Result<Vec<(PathBuf, String, String)>> {
+    let content = fs::read_to_string("/proc/self/mountinfo")?;
+    Ok(content.lines().filter_map(parse_mountinfo_line).collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn system_mounts() -> io::Result<Vec<(PathBuf, String, String)>> {
+    Ok(Vec::new())
+}
+
+/// Parses line like
+/// `36 35 98:0 / /mnt/photos rw,noatime master:1 - ext3 /dev/root rw,errors=continue`,
+/// optional fields end with `-`.
+#[cfg(target_os = "linux")]
+fn parse_mountinfo_line(line: &str) -> Option<(PathBuf, String, String)> {
+    let mut fields = line.split(' ');
+    let mount_point = fields.nth(4)?;
+    let mut fields = fields.skip_while(|field| *field != "-").skip(1);
+    let file_system = fields.next()?;
+    let source = fields.next()?;
+    Some((
+        PathBuf::from(unescape(mount_point)),
+        unescape(file_system),
+        unescape(source),
+    ))
+}
+
+/// Decodes spaces, tabs, newlines and backslashes written as octal escapes, e.g. `\040`.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                decoded.push(code);
+                i += 4;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Device the file is stored on, `None` where the platform does not tell.
+#[cfg(unix)]
+pub(crate) fn device(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn device(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mountinfo_line_skips_optional_fields() {
+        let line = "36 35 98:0 / /mnt/photos rw,noatime master:1 shared:2 - ext3 /dev/root rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some((
+                PathBuf::from("/mnt/photos"),
+                "ext3".to_owned(),
+                "/dev/root".to_owned()
+            ))
+        );
+        let line = "40 35 0:38 / /proc rw,nosuid - proc proc rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some((PathBuf::from("/proc"), "proc".to_owned(), "proc".to_owned()))
+        );
+        assert_eq!(parse_mountinfo_line("36 35 98:0 / /mnt rw"), None);
+    }
+
+    #[test]
+    fn parse_mountinfo_line_decodes_escapes() {
+        let line = r"50 35 0:45 / /media/My\040Photos rw - cifs //nas/photo\134s rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some((
+                PathBuf::from("/media/My Photos"),
+                "cifs".to_owned(),
+                r"//nas/photo\s".to_owned()
+            ))
+        );
+        assert_eq!(unescape(r"tab\011new\012line"), "tab\tnew\nline");
+        assert_eq!(unescape(r"end\04"), r"end\04");
+        assert_eq!(unescape(r"not\999octal"), r"not\999octal");
+    }
+}
@@ -0,0 +1,54 @@
+use crate::CopyOptions;
+use filetime::FileTime;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Carries metadata selected in `options` over from `source` to `destination`
+/// file or directory. `metadata` of `source` is read before transferring,
+/// which changes its access time.
+pub(crate) fn preserve_read_metadata(
+    source: &Path,
+    metadata: &fs::Metadata,
+    destination: &Path,
+    options: &CopyOptions,
+) -> io::Result<()> {
+    // Attributes go before permissions, which can make the destination read-only.
+    if options.preserve_xattrs {
+        copy_xattrs(source, destination)?;
+    }
+    if options.preserve_permissions {
+        fs::set_permissions(destination, metadata.permissions())?;
+    }
+    // Times go last, as changing attributes could update them.
+    if options.preserve_times {
+        filetime::set_file_times(
+            destination,
+            FileTime::from_last_access_time(metadata),
+            FileTime::from_last_modification_time(metadata),
+        )?;
+    }
+    Ok(())
+}
+
+/// Attributes the user is not allowed to set, like `security.*`, are skipped.
+#[cfg(unix)]
+fn copy_xattrs(source: &Path, destination: &Path) -> io::Result<()> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(());
+    }
+    for name in xattr::list(source)? {
+        if let Some(value) = xattr::get(source, &name)? {
+            match xattr::set(destination, &name, &value) {
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {}
+                result => result?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_xattrs(_source: &Path, _destination: &Path) -> io::Result<()> {
+    Ok(())
+}
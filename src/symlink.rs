@@ -0,0 +1,115 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How scanning treats symbolic links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Skip symbolic links.
+    #[default]
+    Ignore,
+    /// Follow links to files, skip links to directories.
+    FollowFiles,
+    /// Follow links to files and directories.
+    FollowAll,
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [SymlinkPolicy; 3] = [
+        SymlinkPolicy::Ignore,
+        SymlinkPolicy::FollowFiles,
+        SymlinkPolicy::FollowAll,
+    ];
+
+    /// Short name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            SymlinkPolicy::Ignore => "ignore",
+            SymlinkPolicy::FollowFiles => "files",
+            SymlinkPolicy::FollowAll => "all",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SymlinkPolicy> {
+        SymlinkPolicy::ALL
+            .iter()
+            .copied()
+            .find(|policy| policy.name() == name)
+    }
+
+    pub(crate) fn follows_files(self) -> bool {
+        self != SymlinkPolicy::Ignore
+    }
+
+    pub(crate) fn follows_directories(self) -> bool {
+        self == SymlinkPolicy::FollowAll
+    }
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            SymlinkPolicy::Ignore => "Ignore symbolic links",
+            SymlinkPolicy::FollowFiles => "Follow symbolic links to files",
+            SymlinkPolicy::FollowAll => "Follow symbolic links to files and directories",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Identity of a directory independent of the path it is reached by.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DirectoryId {
+    device: u64,
+    inode: u64,
+}
+
+#[cfg(unix)]
+impl DirectoryId {
+    pub fn of(path: &Path) -> io::Result<DirectoryId> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = fs::metadata(path)?;
+        Ok(DirectoryId {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        })
+    }
+}
+
+/// Canonical path, device and inode numbers are not available on this platform.
+#[cfg(not(unix))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DirectoryId {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(unix))]
+impl DirectoryId {
+    pub fn of(path: &Path) -> io::Result<DirectoryId> {
+        Ok(DirectoryId {
+            path: fs::canonicalize(path)?,
+        })
+    }
+}
+
+/// Directories from the scanned directory down to the one being scanned,
+/// a link back to any of them would be followed forever.
+pub(crate) struct Ancestors<'a> {
+    pub id: DirectoryId,
+    pub parent: Option<&'a Ancestors<'a>>,
+}
+
+impl Ancestors<'_> {
+    pub fn contains(&self, id: &DirectoryId) -> bool {
+        let mut ancestor = Some(self);
+        while let Some(current) = ancestor {
+            if current.id == *id {
+                return true;
+            }
+            ancestor = current.parent;
+        }
+        false
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::{Datelike, NaiveDateTime};
+use std::path::{Component, Path, PathBuf};
+
+/// Template placing files into date based directories, e.g. `2020/2020-07/photo.jpg`.
+pub const DEFAULT_TEMPLATE: &str = "{year}/{year}-{month}/{original_name}.{ext}";
+
+/// Value of placeholders that can not be determined for a file.
+pub const UNKNOWN_VALUE: &str = "unknown";
+
+/// Value inserted into a path template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    /// Capture year, four digits.
+    Year,
+    /// Capture month, two digits.
+    Month,
+    /// Capture day of month, two digits.
+    Day,
+    /// Camera model from EXIF.
+    CameraModel,
+    /// Source file name without the extension.
+    OriginalName,
+    /// Source file extension, a `.` in front of it is dropped for files without one.
+    Extension,
+    /// Source directory relative to the scanned directory.
+    SourceDir,
+    /// Lowest number, four digits, giving a path not used by another file.
+    Counter,
+    /// First 8 hexadecimal digits of SHA-256 checksum of the content.
+    Hash8,
+}
+
+impl Placeholder {
+    pub const ALL: [Placeholder; 9] = [
+        Placeholder::Year,
+        Placeholder::Month,
+        Placeholder::Day,
+        Placeholder::CameraModel,
+        Placeholder::OriginalName,
+        Placeholder::Extension,
+        Placeholder::SourceDir,
+        Placeholder::Counter,
+        Placeholder::Hash8,
+    ];
+
+    /// Name written between braces in templates.
+    pub fn name(self) -> &'static str {
+        match self {
+            Placeholder::Year => "year",
+            Placeholder::Month => "month",
+            Placeholder::Day => "day",
+            Placeholder::CameraModel => "camera_model",
+            Placeholder::OriginalName => "original_name",
+            Placeholder::Extension => "ext",
+            Placeholder::SourceDir => "source_dir",
+            Placeholder::Counter => "counter",
+            Placeholder::Hash8 => "hash8",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Placeholder> {
+        Placeholder::ALL
+            .iter()
+            .copied()
+            .find(|placeholder| placeholder.name() == name)
+    }
+}
+
+/// Placeholder values of a single file, `None` when unknown or not needed.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub date: Option<NaiveDateTime>,
+    pub camera_model: Option<String>,
+    pub original_name: String,
+    pub extension: String,
+    pub source_dir: PathBuf,
+    pub counter: u32,
+    /// Hexadecimal SHA-256 checksum.
+    pub hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Destination path relative to the output directory with `{placeholder}` values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+impl PathTemplate {
+    /// Parses template, both `/` and `\` separate directories.
+    pub fn parse(text: &str) -> Result<PathTemplate, String> {
+        let mut parts = Vec::new();
+        let mut rest = text.trim();
+        while !rest.is_empty() {
+            let (part, next) = match rest.find('{') {
+                Some(0) => {
+                    let end = rest
+                        .find('}')
+                        .ok_or(format!("Unclosed placeholder in '{}'", text))?;
+                    let placeholder = Placeholder::from_name(&rest[1..end])
+                        .ok_or(format!("Unknown placeholder '{}'", &rest[..=end]))?;
+                    (TemplatePart::Placeholder(placeholder), &rest[end + 1..])
+                }
+                Some(start) => (TemplatePart::Text(rest[..start].to_owned()), &rest[start..]),
+                None => (TemplatePart::Text(rest.to_owned()), ""),
+            };
+            if let TemplatePart::Text(text) = &part {
+                if text.contains('}') {
+                    return Err(format!("Unexpected '}}' in '{}'", text));
+                }
+                let escapes = Path::new(&text.replace('\\', "/"))
+                    .components()
+                    .any(|component| component == Component::ParentDir);
+                if escapes {
+                    return Err("Template can not contain '..'".to_owned());
+                }
+            }
+            parts.push(part);
+            rest = next;
+        }
+
+        match parts.last() {
+            None => Err("Template is empty".to_owned()),
+            Some(TemplatePart::Text(text)) if text.ends_with(['/', '\\']) => {
+                Err("Template must end with a file name".to_owned())
+            }
+            _ => Ok(PathTemplate { parts }),
+        }
+    }
+
+    pub fn uses(&self, placeholder: Placeholder) -> bool {
+        self.parts.contains(&TemplatePart::Placeholder(placeholder))
+    }
+
+    /// Fills the template with `values`. Result is always relative,
+    /// values other than `{source_dir}` can not add directories.
+    pub fn render(&self, values: &TemplateValues) -> PathBuf {
+        let mut text = String::new();
+        for part in &self.parts {
+            let placeholder = match part {
+                TemplatePart::Text(part) => {
+                    text.push_str(part);
+                    continue;
+                }
+                TemplatePart::Placeholder(placeholder) => placeholder,
+            };
+            let value = match placeholder {
+                Placeholder::Year => date_value(values, |date| format!("{:04}", date.year())),
+                Placeholder::Month => date_value(values, |date| format!("{:02}", date.month())),
+                Placeholder::Day => date_value(values, |date| format!("{:02}", date.day())),
+                Placeholder::CameraModel => {
+                    file_name_value(values.camera_model.as_deref().unwrap_or(UNKNOWN_VALUE))
+                }
+                Placeholder::OriginalName => file_name_value(&values.original_name),
+                Placeholder::Extension => {
+                    if values.extension.is_empty() && text.ends_with('.') {
+                        text.pop();
+                    }
+                    file_name_value(&values.extension)
+                }
+                Placeholder::SourceDir => values.source_dir.to_string_lossy().into_owned(),
+                Placeholder::Counter => format!("{:04}", values.counter),
+                Placeholder::Hash8 => match &values.hash {
+                    Some(hash) => hash.chars().take(8).collect(),
+                    None => UNKNOWN_VALUE.to_owned(),
+                },
+            };
+            text.push_str(&value);
+        }
+        text.split(['/', '\\'])
+            .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+            .collect()
+    }
+}
+
+fn date_value<F>(values: &TemplateValues, format: F) -> String
+where
+    F: Fn(NaiveDateTime) -> String,
+{
+    values.date.map_or(UNKNOWN_VALUE.to_owned(), format)
+}
+
+/// Keeps value inside a single path component.
+fn file_name_value(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn render(template: &str, values: &TemplateValues) -> PathBuf {
+        PathTemplate::parse(template).unwrap().render(values)
+    }
+
+    #[test]
+    fn parse_rejects_invalid_templates() {
+        assert!(PathTemplate::parse("{year}/../{original_name}").is_err());
+        assert!(PathTemplate::parse("..\\{original_name}").is_err());
+        assert!(PathTemplate::parse("{year}/").is_err());
+        assert!(PathTemplate::parse("{year}\\").is_err());
+        assert!(PathTemplate::parse("   ").is_err());
+        assert!(PathTemplate::parse("{year}/{name}").is_err());
+        assert!(PathTemplate::parse("{year}/{original_name").is_err());
+        assert!(PathTemplate::parse("{year}/original_name}").is_err());
+    }
+
+    #[test]
+    fn render_fills_placeholders() {
+        let values = TemplateValues {
+            date: NaiveDate::from_ymd_opt(2020, 7, 4).and_then(|date| date.and_hms_opt(12, 0, 0)),
+            original_name: "photo".to_owned(),
+            extension: "jpg".to_owned(),
+            source_dir: PathBuf::from("holiday/beach"),
+            counter: 7,
+            hash: Some("0123456789abcdef".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &values),
+            Path::new("2020/2020-07/photo.jpg")
+        );
+        assert_eq!(
+            render("{source_dir}/{day}-{counter}-{hash8}.{ext}", &values),
+            Path::new("holiday/beach/04-0007-01234567.jpg")
+        );
+        assert_eq!(
+            render("{year}/{camera_model}.{ext}", &TemplateValues::default()),
+            Path::new("unknown/unknown")
+        );
+    }
+
+    #[test]
+    fn render_drops_dot_of_missing_extension() {
+        let values = TemplateValues {
+            original_name: "README".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render("{original_name}.{ext}", &values),
+            Path::new("README")
+        );
+    }
+
+    #[test]
+    fn render_keeps_values_in_one_directory() {
+        let values = TemplateValues {
+            camera_model: Some("EOS 5D/II".to_owned()),
+            original_name: "..\\photo".to_owned(),
+            extension: "jpg".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render("{camera_model}/{original_name}.{ext}", &values),
+            Path::new("EOS 5D_II/.._photo.jpg")
+        );
+    }
+}
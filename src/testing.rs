@@ -0,0 +1,36 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Empty directory for a single test, removed when dropped.
+pub(crate) struct TestDirectory {
+    path: PathBuf,
+}
+
+impl TestDirectory {
+    /// Creates directory unique to the test `name` and this process.
+    pub fn new(name: &str) -> TestDirectory {
+        let path =
+            std::env::temp_dir().join(format!("collect-memories-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDirectory { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes file at `relative` path, creating its directories.
+    pub fn write(&self, relative: &str, content: &str) -> PathBuf {
+        let path = self.path.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::CancelToken;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Ending of hidden temporary files copies are written to before
+/// being renamed to the destination name.
+const TEMPORARY_SUFFIX: &str = ".collect-memories-tmp";
+
+/// Size of chunks files are copied by, progress is reported after each.
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// How file content gets to the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferMode {
+    /// Copy file content.
+    #[default]
+    Copy,
+    /// Rename on the same file system, otherwise copy, verify and remove the source.
+    Move,
+    /// Hard link to the source file, sharing its content.
+    Hardlink,
+    /// Copy-on-write clone sharing data blocks until either file changes,
+    /// supported by btrfs and XFS.
+    Reflink,
+}
+
+impl TransferMode {
+    pub const ALL: [TransferMode; 4] = [
+        TransferMode::Copy,
+        TransferMode::Move,
+        TransferMode::Hardlink,
+        TransferMode::Reflink,
+    ];
+
+    /// Short name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            TransferMode::Copy => "copy",
+            TransferMode::Move => "move",
+            TransferMode::Hardlink => "hardlink",
+            TransferMode::Reflink => "reflink",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TransferMode> {
+        TransferMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+impl fmt::Display for TransferMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            TransferMode::Copy => "Copy files",
+            TransferMode::Move => "Move files, removing them from the source directory",
+            TransferMode::Hardlink => "Hard link files, no extra space on the same disk",
+            TransferMode::Reflink => "Clone files (reflink), no extra space on btrfs and XFS",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// How a single file was transferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transferred {
+    Copied,
+    /// Renamed into the destination, the source is gone.
+    Renamed,
+    HardLinked,
+    Cloned,
+    /// Copied because linking or cloning was not possible.
+    CopiedInstead,
+}
+
+/// Transfers `source` to `destination` by `mode`, replacing existing destination.
+/// Falls back to copying when the destination can not share content with the source.
+///
+/// When `source` is a symbolic link, links and clones share content of the file it
+/// points to.
+///
+/// Copies and clones appear under the destination name only once complete.
+/// `progress` receives count of bytes copied so far while copying,
+/// copying stops with an error when `cancel` is cancelled.
+pub(crate) fn transfer_file<F>(
+    source: &Path,
+    destination: &Path,
+    mode: TransferMode,
+    cancel: &CancelToken,
+    progress: &F,
+) -> io::Result<Transferred>
+where
+    F: Fn(u64),
+{
+    let linked = fs::symlink_metadata(source)?.file_type().is_symlink();
+    let shared = match mode {
+        TransferMode::Copy => None,
+        TransferMode::Move if same_device(source, destination) => {
+            match fs::rename(source, destination) {
+                Ok(()) => return Ok(Transferred::Renamed),
+                // Bind mounts show the same device for different mounts,
+                // renaming across them fails and the file is copied instead.
+                Err(error) if error.kind() == io::ErrorKind::CrossesDevices => None,
+                Err(error) => return Err(error),
+            }
+        }
+        TransferMode::Move => None,
+        TransferMode::Hardlink => Some((
+            replace_destination(destination, |destination| {
+                if linked {
+                    fs::hard_link(fs::canonicalize(source)?, destination)
+                } else {
+                    fs::hard_link(source, destination)
+                }
+            }),
+            Transferred::HardLinked,
+        )),
+        TransferMode::Reflink => Some((
+            write_atomically(destination, |temporary| reflink(source, temporary)),
+            Transferred::Cloned,
+        )),
+    };
+    let copy = |destination| {
+        write_atomically(destination, |temporary| {
+            copy(source, temporary, cancel, progress)
+        })
+    };
+    match shared {
+        Some((Ok(()), transferred)) => Ok(transferred),
+        Some((Err(_), _)) => {
+            copy(destination)?;
+            Ok(Transferred::CopiedInstead)
+        }
+        None => {
+            copy(destination)?;
+            Ok(Transferred::Copied)
+        }
+    }
+}
+
+/// Copies file content by chunks, reporting progress after each of them.
+fn copy<F>(source: &Path, destination: &Path, cancel: &CancelToken, progress: &F) -> io::Result<()>
+where
+    F: Fn(u64),
+{
+    let mut reader = File::open(source)?;
+    let mut writer = File::create(destination)?;
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    let mut copied = 0;
+    loop {
+        cancel.check()?;
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buffer[..read])?;
+        copied += read as u64;
+        progress(copied);
+    }
+}
+
+/// Writes file by `write` to a hidden temporary path next to `destination`,
+/// flushes it to the disk and renames it into place.
+/// Temporary file is removed when writing fails.
+fn write_atomically<F>(destination: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let temporary = temporary_path(destination);
+    let result = write(&temporary)
+        .and_then(|()| OpenOptions::new().write(true).open(&temporary)?.sync_all())
+        .and_then(|()| fs::rename(&temporary, destination));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+fn temporary_path(destination: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(destination.file_name().unwrap_or_default());
+    name.push(TEMPORARY_SUFFIX);
+    destination.with_file_name(name)
+}
+
+fn is_temporary(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with('.') && name.ends_with(TEMPORARY_SUFFIX)
+}
+
+/// Removes temporary files left in `directory` by interrupted transfers.
+/// Returns count of removed files.
+pub(crate) fn remove_temporary_files(directory: &Path) -> io::Result<u32> {
+    let mut removed = 0;
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        if is_temporary(&entry.file_name()) && entry.file_type()?.is_file() {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Links can not replace existing files, so the destination is removed first.
+fn replace_destination<F>(destination: &Path, create: F) -> io::Result<()>
+where
+    F: Fn(&Path) -> io::Result<()>,
+{
+    match create(destination) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            fs::remove_file(destination)?;
+            create(destination)
+        }
+        result => result,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, destination: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source_file = fs::File::open(source)?;
+    let destination_file = fs::File::create(destination)?;
+    // SAFETY: both descriptors are valid open files for the duration of the call.
+    let result = unsafe {
+        libc::ioctl(
+            destination_file.as_raw_fd(),
+            libc::FICLONE,
+            source_file.as_raw_fd(),
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _destination: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Reflinks are not supported on this platform",
+    ))
+}
+
+/// Both paths refer to the same file, e.g. destination is a hard link to the source.
+#[cfg(unix)]
+pub(crate) fn same_file(first: &Path, second: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(first), fs::metadata(second)) {
+        (Ok(first), Ok(second)) => first.dev() == second.dev() && first.ino() == second.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn same_file(first: &Path, second: &Path) -> bool {
+    match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => false,
+    }
+}
+
+/// Source file and destination directory are on the same file system,
+/// so the file can be renamed instead of copied.
+#[cfg(unix)]
+fn same_device(source: &Path, destination: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let destination_directory = destination.parent().unwrap_or(destination);
+    match (fs::metadata(source), fs::metadata(destination_directory)) {
+        (Ok(source), Ok(destination)) => source.dev() == destination.dev(),
+        _ => false,
+    }
+}
+
+/// Compares drive prefixes of canonical paths, e.g. `C:`.
+#[cfg(not(unix))]
+fn same_device(source: &Path, destination: &Path) -> bool {
+    use std::path::Component;
+    let prefix = |path: &Path| match path.canonicalize().ok()?.components().next()? {
+        Component::Prefix(prefix) => Some(prefix.as_os_str().to_owned()),
+        _ => None,
+    };
+    let destination_directory = destination.parent().unwrap_or(destination);
+    match (prefix(source), prefix(destination_directory)) {
+        (Some(source), Some(destination)) => source == destination,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDirectory;
+
+    #[test]
+    fn remove_temporary_files_keeps_other_files() {
+        let dir = TestDirectory::new("temporary-files");
+        let photo = dir.write("a.jpg", "a");
+        let temporary = temporary_path(&dir.path().join("b.jpg"));
+        fs::write(&temporary, "b").unwrap();
+        let hidden = dir.write(".hidden.jpg", "c");
+        fs::create_dir(temporary_path(&dir.path().join("directory"))).unwrap();
+
+        assert_eq!(remove_temporary_files(dir.path()).unwrap(), 1);
+        assert!(photo.exists());
+        assert!(hidden.exists());
+        assert!(!temporary.exists());
+        assert!(temporary_path(&dir.path().join("directory")).exists());
+    }
+
+    #[test]
+    fn failed_write_leaves_no_temporary_file() {
+        let dir = TestDirectory::new("failed-write");
+        let destination = dir.path().join("a.jpg");
+
+        let result = write_atomically(&destination, |temporary| {
+            fs::write(temporary, "partial")?;
+            Err(io::Error::other("interrupted"))
+        });
+
+        assert!(result.is_err());
+        assert!(!destination.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}